@@ -0,0 +1,65 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::language::DetectedLanguage;
+use crate::paywall::PaywallSignal;
+use crate::readability::ContentExtraction;
+use crate::response_cache::sha256_hex;
+use crate::text::TextLink;
+use crate::wire::WireStory;
+
+// One scraped article as it is stored on disk, with enough provenance to trace
+// every document back to the page it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Article {
+    pub url: String,
+    pub canonical_url: String,
+    #[serde(default)]
+    pub guid: Option<String>,
+    pub site: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub section: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub image: Option<String>,
+    pub published: DateTime<FixedOffset>,
+    pub updated: Option<DateTime<FixedOffset>>,
+    pub scraped: DateTime<Utc>,
+    pub language: Option<String>,
+    #[serde(default)]
+    pub detected_language: Option<DetectedLanguage>,
+    #[serde(default)]
+    pub wire: Option<WireStory>,
+    pub paragraphs: Vec<String>,
+    #[serde(default)]
+    pub extraction: Option<ContentExtraction>,
+    #[serde(default)]
+    pub links: Vec<TextLink>,
+    // paywall markers found on the page, and whether the text looks cut short
+    #[serde(default)]
+    pub paywall: Vec<PaywallSignal>,
+    #[serde(default)]
+    pub partial: bool,
+    pub content_hash: String,
+}
+
+pub fn content_hash(paragraphs: &[String]) -> String {
+    sha256_hex(paragraphs.join("\n\n").as_bytes())
+}
+
+pub fn load_article(path: &Path) -> Result<Article> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Cannot read article at {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("Invalid article {}", path.display()))
+}
+
+pub fn is_article_record(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
@@ -0,0 +1,495 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::article::Article;
+use crate::discovery::DiscoveredLink;
+use crate::health::{SelectorBaseline, SelectorHealth};
+use crate::revisions::{diff_paragraphs, HeadlineChange};
+use crate::wire::WireStory;
+
+// What happened to a URL this run, compared with what the state store knew.
+// Failures are reported separately, with their errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchOutcome {
+    New,
+    Updated,
+    Unchanged,
+    Skipped,
+    Duplicate,
+}
+
+// The most recent stored version of an article, keyed by canonical URL.
+pub struct StoredRevision {
+    pub revision: u32,
+    pub title: String,
+    pub content_hash: String,
+    pub paragraphs: Vec<String>,
+    pub path: PathBuf,
+}
+
+impl StoredRevision {
+    pub fn matches(&self, article: &Article) -> bool {
+        self.title == article.title && self.content_hash == article.content_hash
+    }
+}
+
+// SQLite-backed record of every URL the scraper has seen, so later runs only
+// fetch articles that are new, have changed, or failed last time.
+pub struct CrawlState {
+    conn: Mutex<Connection>,
+}
+
+impl CrawlState {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS urls (
+                url           TEXT PRIMARY KEY,
+                site          TEXT NOT NULL,
+                canonical_url TEXT,
+                content_hash  TEXT,
+                first_seen    TEXT NOT NULL,
+                last_fetched  TEXT,
+                status        TEXT NOT NULL,
+                error         TEXT
+            );
+            CREATE INDEX IF NOT EXISTS urls_canonical ON urls (canonical_url);
+            CREATE TABLE IF NOT EXISTS revisions (
+                canonical_url TEXT NOT NULL,
+                revision      INTEGER NOT NULL,
+                fetched_at    TEXT NOT NULL,
+                title         TEXT NOT NULL,
+                content_hash  TEXT NOT NULL,
+                paragraphs    TEXT NOT NULL,
+                diff          TEXT NOT NULL,
+                path          TEXT NOT NULL,
+                PRIMARY KEY (canonical_url, revision)
+            );
+            CREATE TABLE IF NOT EXISTS wire_stories (
+                fingerprint   TEXT NOT NULL,
+                canonical_url TEXT NOT NULL,
+                site          TEXT NOT NULL,
+                service       TEXT NOT NULL,
+                first_seen    TEXT NOT NULL,
+                PRIMARY KEY (fingerprint, canonical_url)
+            );
+            CREATE TABLE IF NOT EXISTS selector_checks (
+                site          TEXT NOT NULL,
+                checked_at    TEXT NOT NULL,
+                links         INTEGER,
+                sampled       INTEGER NOT NULL,
+                title         INTEGER NOT NULL,
+                date          INTEGER NOT NULL,
+                body          INTEGER,
+                paragraphs    INTEGER NOT NULL,
+                degraded      INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS selector_checks_site ON selector_checks (site, checked_at);",
+        )?;
+        Ok(CrawlState {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    // A URL is refetched if it is new, failed last time, its feed or sitemap
+    // says it was modified after our last fetch, or it is still younger than
+    // `refetch_window`. Section pages give no dates at all, so the window is
+    // what picks up corrections to stories found only there. An article's age
+    // is counted from its publication date, or from when we first saw it.
+    pub fn should_fetch(&self, link: &DiscoveredLink, refetch_window: Duration) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let row: Option<(String, String, Option<String>)> = conn
+            .query_row(
+                "SELECT status, first_seen, last_fetched FROM urls WHERE url = ?1",
+                params![link.url],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+
+        let Some((status, first_seen, last_fetched)) = row else {
+            return Ok(true);
+        };
+        // duplicates are judged like the article they duplicate
+        if status == "failed" {
+            return Ok(true);
+        }
+        let Some(last_fetched) =
+            last_fetched.and_then(|date| DateTime::parse_from_rfc3339(&date).ok())
+        else {
+            return Ok(true);
+        };
+        if link.updated.or(link.published) > Some(last_fetched) {
+            return Ok(true);
+        }
+
+        let first_seen = DateTime::parse_from_rfc3339(&first_seen)?;
+        let age = Utc::now() - link.published.unwrap_or(first_seen).with_timezone(&Utc);
+        Ok(age < refetch_window)
+    }
+
+    // `changed` says whether a new revision of the article was stored, which
+    // also catches headline-only edits that leave the content hash alone.
+    pub fn record_success(
+        &self,
+        site: &str,
+        article: &Article,
+        changed: bool,
+    ) -> Result<FetchOutcome> {
+        let conn = self.conn.lock().unwrap();
+        // a story first saved under another link is not new either
+        let seen: Option<String> = conn
+            .query_row(
+                "SELECT url FROM urls WHERE (url = ?1 OR canonical_url = ?2) AND status = 'ok'",
+                params![article.url, article.canonical_url],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO urls (url, site, canonical_url, content_hash, first_seen, last_fetched, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5, 'ok')
+             ON CONFLICT (url) DO UPDATE SET
+                canonical_url = excluded.canonical_url,
+                content_hash = excluded.content_hash,
+                last_fetched = excluded.last_fetched,
+                status = 'ok',
+                error = NULL",
+            params![article.url, site, article.canonical_url, article.content_hash, now],
+        )?;
+
+        Ok(match (seen, changed) {
+            (None, _) => FetchOutcome::New,
+            (Some(_), true) => FetchOutcome::Updated,
+            (Some(_), false) => FetchOutcome::Unchanged,
+        })
+    }
+
+    // A link that turned out to be an article already claimed this run, kept
+    // so later runs skip it like the article itself. `canonical_url` is None
+    // when the link was a duplicate before it was fetched. A URL that was
+    // saved successfully before keeps that status.
+    pub fn record_duplicate(
+        &self,
+        site: &str,
+        url: &str,
+        canonical_url: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO urls (url, site, canonical_url, first_seen, last_fetched, status)
+             VALUES (?1, ?2, ?3, ?4, ?4, 'duplicate')
+             ON CONFLICT (url) DO UPDATE SET
+                canonical_url = coalesce(excluded.canonical_url, canonical_url),
+                last_fetched = excluded.last_fetched,
+                status = 'duplicate',
+                error = NULL
+             WHERE status <> 'ok'",
+            params![url, site, canonical_url, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn record_failure(&self, site: &str, url: &str, error: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO urls (url, site, first_seen, last_fetched, status, error)
+             VALUES (?1, ?2, ?3, ?3, 'failed', ?4)
+             ON CONFLICT (url) DO UPDATE SET
+                last_fetched = excluded.last_fetched,
+                status = 'failed',
+                error = excluded.error",
+            params![url, site, now, error],
+        )?;
+        Ok(())
+    }
+
+    pub fn latest_revision(&self, canonical_url: &str) -> Result<Option<StoredRevision>> {
+        let conn = self.conn.lock().unwrap();
+        let row: Option<(u32, String, String, String, String)> = conn
+            .query_row(
+                "SELECT revision, title, content_hash, paragraphs, path FROM revisions
+                 WHERE canonical_url = ?1 ORDER BY revision DESC LIMIT 1",
+                params![canonical_url],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let Some((revision, title, content_hash, paragraphs, path)) = row else {
+            return Ok(None);
+        };
+        Ok(Some(StoredRevision {
+            revision,
+            title,
+            content_hash,
+            paragraphs: serde_json::from_str(&paragraphs)?,
+            path: PathBuf::from(path),
+        }))
+    }
+
+    // Stores the article as the next revision after `previous`, along with its
+    // paragraph diff against that revision. Returns the new revision number.
+    pub fn add_revision(
+        &self,
+        article: &Article,
+        previous: Option<&StoredRevision>,
+        path: &Path,
+    ) -> Result<u32> {
+        let (revision, diff) = match previous {
+            Some(previous) => (
+                previous.revision + 1,
+                diff_paragraphs(&previous.paragraphs, &article.paragraphs),
+            ),
+            None => (1, Vec::new()),
+        };
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO revisions
+                (canonical_url, revision, fetched_at, title, content_hash, paragraphs, diff, path)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                article.canonical_url,
+                revision,
+                article.scraped.to_rfc3339(),
+                article.title,
+                article.content_hash,
+                serde_json::to_string(&article.paragraphs)?,
+                serde_json::to_string(&diff)?,
+                path.to_string_lossy(),
+            ],
+        )?;
+        Ok(revision)
+    }
+
+    pub fn headline_changes(&self) -> Result<Vec<HeadlineChange>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT new.canonical_url, new.revision, new.fetched_at, old.title, new.title
+             FROM revisions AS new
+             JOIN revisions AS old
+               ON old.canonical_url = new.canonical_url AND old.revision = new.revision - 1
+             WHERE old.title != new.title
+             ORDER BY new.fetched_at",
+        )?;
+        let changes = statement
+            .query_map([], |row| {
+                Ok(HeadlineChange {
+                    canonical_url: row.get(0)?,
+                    revision: row.get(1)?,
+                    fetched_at: row.get(2)?,
+                    old_title: row.get(3)?,
+                    new_title: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(changes)
+    }
+
+    // Remembers a wire story and returns the (site, canonical URL) of every
+    // copy of it already seen on other sites.
+    pub fn record_wire_story(
+        &self,
+        site: &str,
+        article: &Article,
+        wire: &WireStory,
+    ) -> Result<Vec<(String, String)>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR IGNORE INTO wire_stories
+                (fingerprint, canonical_url, site, service, first_seen)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                wire.fingerprint,
+                article.canonical_url,
+                site,
+                serde_json::to_string(&wire.service)?,
+                Utc::now().to_rfc3339(),
+            ],
+        )?;
+
+        let mut statement = conn.prepare(
+            "SELECT site, canonical_url FROM wire_stories
+             WHERE fingerprint = ?1 AND site != ?2
+             ORDER BY first_seen",
+        )?;
+        let copies = statement
+            .query_map(params![wire.fingerprint, site], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(copies)
+    }
+
+    pub fn record_selector_check(
+        &self,
+        site: &str,
+        health: &SelectorHealth,
+        degraded: bool,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO selector_checks
+                (site, checked_at, links, sampled, title, date, body, paragraphs, degraded)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                site,
+                Utc::now().to_rfc3339(),
+                health.links,
+                health.sampled,
+                health.title,
+                health.date,
+                health.body,
+                health.paragraphs,
+                degraded,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Averages the last `checks` healthy checks of a site. Hit rates skip
+    // checks that sampled no pages, and None means there is nothing to compare.
+    pub fn selector_baseline(&self, site: &str, checks: usize) -> Result<Option<SelectorBaseline>> {
+        let conn = self.conn.lock().unwrap();
+        let baseline = conn.query_row(
+            "SELECT COUNT(*), AVG(links),
+                    AVG(title * 1.0 / NULLIF(sampled, 0)),
+                    AVG(date * 1.0 / NULLIF(sampled, 0)),
+                    AVG(body * 1.0 / NULLIF(sampled, 0)),
+                    AVG(paragraphs * 1.0 / NULLIF(sampled, 0))
+             FROM (SELECT * FROM selector_checks
+                   WHERE site = ?1 AND degraded = 0
+                   ORDER BY checked_at DESC LIMIT ?2)",
+            params![site, checks],
+            |row| {
+                Ok(SelectorBaseline {
+                    checks: row.get(0)?,
+                    links: row.get(1)?,
+                    title: row.get(2)?,
+                    date: row.get(3)?,
+                    body: row.get(4)?,
+                    paragraphs: row.get(5)?,
+                })
+            },
+        )?;
+        Ok((baseline.checks > 0).then_some(baseline))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use super::*;
+
+    const URL: &str = "https://www.cbc.ca/news/story-1.1";
+
+    // A state where URL was first seen `age` ago and last fetched `fetched` ago.
+    fn state_with(status: &str, age: Duration, fetched: Duration) -> CrawlState {
+        let state = CrawlState::open(Path::new(":memory:")).unwrap();
+        let now = Utc::now();
+        state
+            .conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO urls (url, site, first_seen, last_fetched, status)
+                 VALUES (?1, 'cbc', ?2, ?3, ?4)",
+                params![
+                    URL,
+                    (now - age).to_rfc3339(),
+                    (now - fetched).to_rfc3339(),
+                    status
+                ],
+            )
+            .unwrap();
+        state
+    }
+
+    fn ago(duration: Duration) -> Option<DateTime<FixedOffset>> {
+        Some((Utc::now() - duration).into())
+    }
+
+    #[test]
+    fn fetches_new_and_failed_urls() {
+        let state = state_with("failed", Duration::days(30), Duration::days(29));
+        let link = DiscoveredLink::from_html(URL.into());
+        assert!(state.should_fetch(&link, Duration::zero()).unwrap());
+        let other = DiscoveredLink::from_html("https://www.cbc.ca/news/other-1.2".into());
+        assert!(state.should_fetch(&other, Duration::zero()).unwrap());
+    }
+
+    #[test]
+    fn refetches_when_modified_after_the_last_fetch() {
+        let state = state_with("ok", Duration::days(30), Duration::days(10));
+        let mut link = DiscoveredLink::from_html(URL.into());
+        link.published = ago(Duration::days(30));
+        assert!(!state.should_fetch(&link, Duration::days(2)).unwrap());
+
+        // an old publication date doesn't hide a newer modification date
+        link.updated = ago(Duration::days(1));
+        assert!(state.should_fetch(&link, Duration::days(2)).unwrap());
+    }
+
+    #[test]
+    fn refetches_recent_articles_within_the_window() {
+        // section pages give no dates, so age is counted from first sight
+        let state = state_with("ok", Duration::hours(20), Duration::hours(1));
+        let link = DiscoveredLink::from_html(URL.into());
+        assert!(state.should_fetch(&link, Duration::days(2)).unwrap());
+        assert!(!state.should_fetch(&link, Duration::hours(12)).unwrap());
+
+        let state = state_with("ok", Duration::days(5), Duration::days(1));
+        assert!(!state.should_fetch(&link, Duration::days(2)).unwrap());
+    }
+
+    #[test]
+    fn degraded_checks_stay_out_of_the_baseline() {
+        let state = CrawlState::open(Path::new(":memory:")).unwrap();
+        assert!(state.selector_baseline("cbc", 5).unwrap().is_none());
+
+        let check = |links, title| SelectorHealth {
+            links: Some(links),
+            sampled: 4,
+            title,
+            date: 4,
+            body: None,
+            paragraphs: 4,
+        };
+        state
+            .record_selector_check("cbc", &check(20, 4), false)
+            .unwrap();
+        state
+            .record_selector_check("cbc", &check(10, 2), false)
+            .unwrap();
+        state
+            .record_selector_check("cbc", &check(0, 0), true)
+            .unwrap();
+        state
+            .record_selector_check("star", &check(50, 4), false)
+            .unwrap();
+
+        let baseline = state.selector_baseline("cbc", 5).unwrap().unwrap();
+        assert_eq!(baseline.checks, 2);
+        assert_eq!(baseline.links, Some(15.0));
+        assert_eq!(baseline.title, Some(0.75));
+        assert_eq!(baseline.date, Some(1.0));
+        assert_eq!(baseline.body, None);
+    }
+}
@@ -0,0 +1,291 @@
+use anyhow::{anyhow, Result};
+use chrono::{
+    DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone, Utc,
+};
+use chrono_tz::{America, Tz};
+use regex::Regex;
+
+// All three outlets publish from Toronto, so dates without a zone are read as
+// Eastern time, with daylight saving time where it applies.
+const DEFAULT_ZONE: Tz = America::Toronto;
+
+pub fn now() -> DateTime<FixedOffset> {
+    let now = Utc::now().with_timezone(&DEFAULT_ZONE);
+    now.with_timezone(&now.offset().fix())
+}
+
+// Wall-clock time in `zone`. Times repeated when clocks go back take the
+// first occurrence; times skipped when they go forward are read an hour later.
+fn localize(zone: Tz, date: &NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+    let local = zone.from_local_datetime(date).earliest().or_else(|| {
+        zone.from_local_datetime(&(*date + Duration::hours(1)))
+            .earliest()
+    })?;
+    Some(local.with_timezone(&local.offset().fix()))
+}
+
+fn month_from_name(name: &str) -> Option<u32> {
+    let prefix = name.get(..3)?.to_lowercase();
+    let month = match prefix.as_str() {
+        "jan" => 1,
+        "feb" => 2,
+        "mar" => 3,
+        "apr" => 4,
+        "may" => 5,
+        "jun" => 6,
+        "jul" => 7,
+        "aug" => 8,
+        "sep" => 9,
+        "oct" => 10,
+        "nov" => 11,
+        "dec" => 12,
+        _ => return None,
+    };
+    Some(month)
+}
+
+// "EST" and "EDT" name a fixed offset, while "ET" means whichever of the two
+// was in force on the day.
+fn localize_abbreviation(abbr: &str, date: &NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+    let zone = match abbr {
+        "NT" => America::St_Johns,
+        "AT" => America::Halifax,
+        "ET" => America::Toronto,
+        "CT" => America::Winnipeg,
+        "MT" => America::Edmonton,
+        "PT" => America::Vancouver,
+        _ => {
+            let hours_west = match abbr {
+                "UTC" | "GMT" | "Z" => 0.,
+                "NDT" => 2.5,
+                "ADT" => 3.,
+                "NST" => 3.5,
+                "AST" | "EDT" => 4.,
+                "EST" | "CDT" => 5.,
+                "CST" | "MDT" => 6.,
+                "MST" | "PDT" => 7.,
+                "PST" => 8.,
+                _ => return None,
+            };
+            let offset = FixedOffset::west_opt((hours_west * 3600.) as i32)?;
+            return offset.from_local_datetime(date).single();
+        }
+    };
+    localize(zone, date)
+}
+
+fn parse_relative(raw: &str, now: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+    let lower = raw.to_lowercase();
+    if lower.contains("just now") {
+        return Some(now);
+    }
+    if lower.contains("yesterday") {
+        return Some(now - Duration::days(1));
+    }
+
+    let relative_regex =
+        Regex::new(r"(?P<n>\d+|an?) (?P<unit>second|sec|minute|min|hour|hr|day|week)s? ago")
+            .unwrap();
+    let caps = relative_regex.captures(&lower)?;
+    let n = match &caps["n"] {
+        "a" | "an" => 1,
+        n => n.parse::<i64>().ok()?,
+    };
+    let delta = match &caps["unit"] {
+        "second" | "sec" => Duration::seconds(n),
+        "minute" | "min" => Duration::minutes(n),
+        "hour" | "hr" => Duration::hours(n),
+        "day" => Duration::days(n),
+        _ => Duration::weeks(n),
+    };
+    Some(now - delta)
+}
+
+// Handles display dates such as "Published Mar 3, 2023", "Fri., March 3, 2023"
+// "March 3, 2023 at 4:15 p.m. EST" and CBC's "Mar 03, 2023 4:00 AM EST".
+fn parse_written(raw: &str) -> Option<DateTime<FixedOffset>> {
+    let written_regex = Regex::new(
+        r"(?x)
+        (?P<month>[A-Za-z]{3,9})\.?\s+(?P<day>\d{1,2}),?\s+(?P<year>\d{4})
+        (?:,?\s*(?:at\s+)?(?P<hour>\d{1,2}):(?P<min>\d{2})\s*(?P<ampm>(?i:[ap]))\.?(?i:m)\.?)?
+        (?:\s+(?P<tz>[A-Z]{1,4})\b)?",
+    )
+    .unwrap();
+
+    let caps = written_regex
+        .captures_iter(raw)
+        .find(|caps| month_from_name(&caps["month"]).is_some())?;
+    let month = month_from_name(&caps["month"])?;
+    let day = caps["day"].parse().ok()?;
+    let year = caps["year"].parse().ok()?;
+    let date = NaiveDate::from_ymd_opt(year, month, day)?;
+
+    let time = match (caps.name("hour"), caps.name("min")) {
+        (Some(hour), Some(min)) => {
+            let mut hour: u32 = hour.as_str().parse().ok()?;
+            let min: u32 = min.as_str().parse().ok()?;
+            let pm = caps
+                .name("ampm")
+                .is_some_and(|ampm| ampm.as_str().eq_ignore_ascii_case("p"));
+            hour %= 12;
+            if pm {
+                hour += 12;
+            }
+            NaiveTime::from_hms_opt(hour, min, 0)?
+        }
+        _ => NaiveTime::from_hms_opt(0, 0, 0)?,
+    };
+
+    let date = NaiveDateTime::new(date, time);
+    caps.name("tz")
+        .and_then(|tz| localize_abbreviation(tz.as_str(), &date))
+        .or_else(|| localize(DEFAULT_ZONE, &date))
+}
+
+pub fn parse_pub_date(raw: &str, now: DateTime<FixedOffset>) -> Result<DateTime<FixedOffset>> {
+    let raw = raw.trim();
+
+    // machine-readable `datetime` attributes
+    if let Ok(date) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(date);
+    }
+    if let Ok(date) = DateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M:%S%.f%z") {
+        return Ok(date);
+    }
+    if let Ok(date) = DateTime::parse_from_rfc2822(raw) {
+        return Ok(date);
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(raw, format) {
+            if let Some(date) = localize(DEFAULT_ZONE, &date) {
+                return Ok(date);
+            }
+        }
+    }
+    if let Some(date) = raw
+        .get(..10)
+        .and_then(|prefix| NaiveDate::parse_from_str(prefix, "%Y-%m-%d").ok())
+    {
+        let date = date.and_hms_opt(0, 0, 0).unwrap();
+        if let Some(date) = localize(DEFAULT_ZONE, &date) {
+            return Ok(date);
+        }
+    }
+
+    // display text, where an absolute date wins over "Last updated 2 hours ago"
+    if let Some(date) = parse_written(raw) {
+        return Ok(date);
+    }
+    if let Some(date) = parse_relative(raw, now) {
+        return Ok(date);
+    }
+
+    Err(anyhow!("Cannot parse date {raw:?}."))
+}
+
+// Directory names are written as YYYY-MM-DD. Older scrapes used day-month-year with
+// either a month number or a month name, e.g. "3-3-2023" or "3-March-2023".
+pub fn parse_dir_date(name: &str) -> Result<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(name, "%Y-%m-%d") {
+        return Ok(date);
+    }
+
+    let parts: Vec<&str> = name.split('-').collect();
+    if let [day, month, year] = parts[..] {
+        let month = month.parse().ok().or_else(|| month_from_name(month));
+        if let (Ok(day), Some(month), Ok(year)) = (day.parse(), month, year.parse()) {
+            if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
+                return Ok(date);
+            }
+        }
+    }
+
+    Err(anyhow!("Cannot parse directory date {name:?}."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339("2023-03-05T12:00:00-05:00").unwrap()
+    }
+
+    fn parse(raw: &str) -> String {
+        parse_pub_date(raw, now()).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn parses_machine_readable_dates() {
+        assert_eq!(parse("2023-03-03T16:15:00Z"), "2023-03-03T16:15:00+00:00");
+        assert_eq!(
+            parse("2023-03-03T11:15:00.000-0500"),
+            "2023-03-03T11:15:00-05:00"
+        );
+        assert_eq!(
+            parse("Fri, 03 Mar 2023 16:15:00 GMT"),
+            "2023-03-03T16:15:00+00:00"
+        );
+        assert_eq!(parse("2023-03-03"), "2023-03-03T00:00:00-05:00");
+    }
+
+    #[test]
+    fn parses_display_dates() {
+        assert_eq!(parse("Published Mar 3, 2023"), "2023-03-03T00:00:00-05:00");
+        assert_eq!(
+            parse("March 3, 2023 at 4:15 p.m. EST"),
+            "2023-03-03T16:15:00-05:00"
+        );
+        assert_eq!(
+            parse("Mar 03, 2023 4:00 AM EST"),
+            "2023-03-03T04:00:00-05:00"
+        );
+        assert_eq!(
+            parse("Mar 03, 2023 12:30 PM PST"),
+            "2023-03-03T12:30:00-08:00"
+        );
+    }
+
+    #[test]
+    fn reads_eastern_time_with_daylight_saving() {
+        assert_eq!(
+            parse("July 4, 2023 2:15 p.m. ET"),
+            "2023-07-04T14:15:00-04:00"
+        );
+        assert_eq!(
+            parse("Jan 4, 2023 2:15 p.m. ET"),
+            "2023-01-04T14:15:00-05:00"
+        );
+        assert_eq!(parse("2023-07-04 09:30:00"), "2023-07-04T09:30:00-04:00");
+        assert_eq!(parse("July 4, 2023"), "2023-07-04T00:00:00-04:00");
+        // explicit standard time stays standard time
+        assert_eq!(
+            parse("July 4, 2023 2:15 p.m. EST"),
+            "2023-07-04T14:15:00-05:00"
+        );
+        assert_eq!(
+            parse("July 4, 2023 2:15 p.m. PT"),
+            "2023-07-04T14:15:00-07:00"
+        );
+        // 2:30 a.m. doesn't exist on the morning clocks go forward
+        assert_eq!(
+            parse("March 12, 2023 2:30 a.m. ET"),
+            "2023-03-12T03:30:00-04:00"
+        );
+    }
+
+    #[test]
+    fn parses_relative_dates() {
+        assert_eq!(parse("2 hours ago"), "2023-03-05T10:00:00-05:00");
+        assert_eq!(parse("Yesterday"), "2023-03-04T12:00:00-05:00");
+        assert!(parse_pub_date("sometime soon", now()).is_err());
+    }
+
+    #[test]
+    fn parses_directory_dates() {
+        let date = NaiveDate::from_ymd_opt(2023, 3, 3).unwrap();
+        assert_eq!(parse_dir_date("2023-03-03").unwrap(), date);
+        assert_eq!(parse_dir_date("3-3-2023").unwrap(), date);
+        assert_eq!(parse_dir_date("3-March-2023").unwrap(), date);
+    }
+}
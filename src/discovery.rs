@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, FixedOffset};
+use reqwest::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkSource {
+    Html,
+    Feed,
+    Sitemap,
+}
+
+// An article URL found on a section page, in a feed or in a sitemap, along with
+// whatever the source told us about it. Titles and dates from feeds and sitemaps
+// are used when the article page itself doesn't yield them.
+#[derive(Debug, Clone)]
+pub struct DiscoveredLink {
+    pub url: String,
+    pub source: LinkSource,
+    pub title: Option<String>,
+    pub published: Option<DateTime<FixedOffset>>,
+    // when the source says the article last changed (Atom <updated>, sitemap
+    // <lastmod>), which is what decides whether it is refetched
+    pub updated: Option<DateTime<FixedOffset>>,
+    pub guid: Option<String>,
+}
+
+impl DiscoveredLink {
+    pub fn from_html(url: String) -> Self {
+        DiscoveredLink {
+            url,
+            source: LinkSource::Html,
+            title: None,
+            published: None,
+            updated: None,
+            guid: None,
+        }
+    }
+}
+
+// Query parameters that only say where a reader clicked from.
+const TRACKING_PARAMS: [&str; 8] = [
+    "fbclid", "gclid", "dclid", "mc_cid", "mc_eid", "cmp", "ocid", "ref",
+];
+
+fn is_tracking_param(key: &str) -> bool {
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key)
+}
+
+// Drops the fragment and tracking parameters, leaving a URL that still points
+// at exactly the page the server would have served.
+pub fn clean_url(href: &str) -> Option<String> {
+    let mut url = Url::parse(href).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    url.set_fragment(None);
+
+    let query: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !is_tracking_param(key))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    if query.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(query);
+    }
+    Some(url.into())
+}
+
+// The form used to decide whether two URLs are the same article: cleaned,
+// always https, and without a trailing slash.
+pub fn normalize_url(href: &str) -> Option<String> {
+    let mut url = Url::parse(&clean_url(href)?).ok()?;
+    url.set_scheme("https").ok()?;
+    let path = url.path().trim_end_matches('/').to_string();
+    if !path.is_empty() {
+        url.set_path(&path);
+    }
+    Some(url.into())
+}
+
+// Resolves an href found on `page_href` into a cleaned absolute URL.
+pub fn resolve_link(page_href: &str, href: &str) -> Option<String> {
+    let url = Url::parse(page_href).ok()?.join(href.trim()).ok()?;
+    clean_url(url.as_str())
+}
+
+// Drops repeated URLs, keeping the first occurrence. Sources are passed in
+// order of preference. URLs are cleaned on the way through and compared in
+// normalized form, so the same article linked from several cards or with
+// different tracking parameters is only fetched once.
+pub fn merge_links(sources: Vec<Vec<DiscoveredLink>>) -> Vec<DiscoveredLink> {
+    let mut seen = HashSet::new();
+    sources
+        .into_iter()
+        .flatten()
+        .filter_map(|mut link| {
+            link.url = clean_url(&link.url)?;
+            let key = normalize_url(&link.url)?;
+            seen.insert(key).then_some(link)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_link(url: &str, title: &str) -> DiscoveredLink {
+        DiscoveredLink {
+            title: Some(title.into()),
+            source: LinkSource::Feed,
+            ..DiscoveredLink::from_html(url.into())
+        }
+    }
+
+    #[test]
+    fn cleans_fragments_and_tracking_parameters() {
+        assert_eq!(
+            clean_url("https://www.cbc.ca/news/story-1.1?utm_source=x&id=4&fbclid=y#comments")
+                .as_deref(),
+            Some("https://www.cbc.ca/news/story-1.1?id=4")
+        );
+        assert_eq!(clean_url("mailto:tips@cbc.ca"), None);
+    }
+
+    #[test]
+    fn normalizes_scheme_and_trailing_slash() {
+        let normalized = normalize_url("https://www.cbc.ca/news/story-1.1/");
+        assert_eq!(
+            normalized.as_deref(),
+            Some("https://www.cbc.ca/news/story-1.1")
+        );
+        assert_eq!(
+            normalize_url("http://www.cbc.ca/news/story-1.1?utm_medium=rss"),
+            normalized
+        );
+        assert_eq!(
+            normalize_url("https://www.cbc.ca/").as_deref(),
+            Some("https://www.cbc.ca/")
+        );
+    }
+
+    #[test]
+    fn merge_keeps_the_first_source_for_each_article() {
+        let links = merge_links(vec![
+            vec![feed_link(
+                "https://www.cbc.ca/news/story-1.1?utm_source=rss",
+                "From the feed",
+            )],
+            vec![
+                DiscoveredLink::from_html("http://www.cbc.ca/news/story-1.1/#top".into()),
+                DiscoveredLink::from_html("https://www.cbc.ca/news/other-1.2".into()),
+                DiscoveredLink::from_html("not a url".into()),
+            ],
+        ]);
+        let urls: Vec<&str> = links.iter().map(|link| link.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://www.cbc.ca/news/story-1.1",
+                "https://www.cbc.ca/news/other-1.2"
+            ]
+        );
+        assert_eq!(links[0].title.as_deref(), Some("From the feed"));
+        assert_eq!(links[0].source, LinkSource::Feed);
+    }
+}
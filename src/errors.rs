@@ -0,0 +1,91 @@
+use std::{fmt, io};
+
+use serde::Serialize;
+
+// Why an article couldn't be scraped. Functions still return anyhow::Result;
+// these are raised with `.into()` and recovered with `ScrapeError::classify`.
+#[derive(Debug)]
+pub enum ScrapeError {
+    Network(String),
+    HttpStatus(u16),
+    RobotsDisallowed,
+    MissingTitle,
+    MissingDate,
+    MissingBody,
+    EmptyBody,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    Network,
+    HttpStatus,
+    RobotsDisallowed,
+    MissingTitle,
+    MissingDate,
+    MissingBody,
+    EmptyBody,
+    Io,
+    Other,
+}
+
+impl ErrorCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorCategory::Network => "network",
+            ErrorCategory::HttpStatus => "HTTP status",
+            ErrorCategory::RobotsDisallowed => "disallowed by robots.txt",
+            ErrorCategory::MissingTitle => "missing title",
+            ErrorCategory::MissingDate => "missing date",
+            ErrorCategory::MissingBody => "missing body",
+            ErrorCategory::EmptyBody => "empty body",
+            ErrorCategory::Io => "IO",
+            ErrorCategory::Other => "other",
+        }
+    }
+}
+
+impl ScrapeError {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ScrapeError::Network(_) => ErrorCategory::Network,
+            ScrapeError::HttpStatus(_) => ErrorCategory::HttpStatus,
+            ScrapeError::RobotsDisallowed => ErrorCategory::RobotsDisallowed,
+            ScrapeError::MissingTitle => ErrorCategory::MissingTitle,
+            ScrapeError::MissingDate => ErrorCategory::MissingDate,
+            ScrapeError::MissingBody => ErrorCategory::MissingBody,
+            ScrapeError::EmptyBody => ErrorCategory::EmptyBody,
+        }
+    }
+
+    // Errors that never went through ScrapeError are classified by their
+    // source where that's unambiguous.
+    pub fn classify(err: &anyhow::Error) -> ErrorCategory {
+        if let Some(err) = err.downcast_ref::<ScrapeError>() {
+            return err.category();
+        }
+        if err.downcast_ref::<reqwest::Error>().is_some() {
+            return ErrorCategory::Network;
+        }
+        if err.downcast_ref::<io::Error>().is_some() {
+            return ErrorCategory::Io;
+        }
+        ErrorCategory::Other
+    }
+}
+
+impl fmt::Display for ScrapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScrapeError::Network(message) => write!(f, "Network error: {message}"),
+            ScrapeError::HttpStatus(status) => write!(f, "HTTP {status}"),
+            ScrapeError::RobotsDisallowed => write!(f, "Disallowed by robots.txt."),
+            ScrapeError::MissingTitle => write!(f, "Couldn't find title."),
+            ScrapeError::MissingDate => write!(f, "Couldn't find timestamp."),
+            ScrapeError::MissingBody => write!(f, "Couldn't find article body."),
+            ScrapeError::EmptyBody => write!(f, "Article body has no paragraphs."),
+        }
+    }
+}
+
+impl std::error::Error for ScrapeError {}
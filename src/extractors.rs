@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+
+use crate::dates::{self, parse_pub_date};
+use crate::discovery::resolve_link;
+use crate::errors::ScrapeError;
+use crate::metadata::PageMetadata;
+use crate::readability::{extract_main_content, ContentExtraction};
+use crate::site_config::NewsSite;
+use crate::text::{Paragraph, TextNormalizer};
+
+// Pulls the parts of an article out of a parsed page. The default methods are
+// driven by the selectors in the site config; outlets with quirky markup
+// override only the pieces that need it. Extractors are shared between
+// scraping tasks, hence Send + Sync.
+pub trait SiteExtractor: Send + Sync {
+    // Structured metadata is preferred over the selector-based methods below,
+    // which are used for whatever it doesn't provide.
+    fn metadata(&self, doc: &Html, _site: &NewsSite) -> PageMetadata {
+        PageMetadata::from_doc(doc)
+    }
+
+    fn title(&self, doc: &Html, _site: &NewsSite) -> Result<String> {
+        let title_selector = Selector::parse("title").expect("unable to construct selector.");
+        let title_elem = doc
+            .select(&title_selector)
+            .next()
+            .ok_or(ScrapeError::MissingTitle)?;
+        let title_text = title_elem.text().next().ok_or(ScrapeError::MissingTitle)?;
+        Ok(String::from(title_text))
+    }
+
+    fn date(&self, doc: &Html, site: &NewsSite) -> Result<DateTime<FixedOffset>> {
+        get_date(doc, &site.date_selectors)
+    }
+
+    fn paragraphs(
+        &self,
+        doc: &Html,
+        site: &NewsSite,
+        normalizer: &TextNormalizer,
+    ) -> Result<Vec<Paragraph>> {
+        let body = site.body.as_deref().ok_or(ScrapeError::MissingBody)?;
+        let story = get_story_div(doc, body)?;
+
+        // get p elements
+        let par_selector = Selector::parse(&site.par_class).expect("Unable to construct selector.");
+        Ok(story
+            .select(&par_selector)
+            .filter_map(|par| normalizer.paragraph(par))
+            .collect())
+    }
+
+    // The configured selectors win; when they find nothing the main content is
+    // picked out by readability-style scoring instead.
+    fn content(&self, doc: &Html, site: &NewsSite) -> Result<(Vec<Paragraph>, ContentExtraction)> {
+        let normalizer = TextNormalizer::new(&site.text);
+        let selector_err = match self.paragraphs(doc, site, &normalizer) {
+            Ok(paragraphs) if !paragraphs.is_empty() => {
+                return Ok((paragraphs, ContentExtraction::selectors()))
+            }
+            Ok(_) => ScrapeError::EmptyBody.into(),
+            Err(err) => err,
+        };
+        extract_main_content(doc, &normalizer).ok_or(selector_err)
+    }
+
+    fn authors(&self, doc: &Html, site: &NewsSite) -> Vec<String> {
+        let author_selector =
+            Selector::parse("meta[name=\"author\"]").expect("Unable to construct selector.");
+        let authors: Vec<String> = doc
+            .select(&author_selector)
+            .filter_map(|elem| elem.value().attr("content"))
+            .map(String::from)
+            .collect();
+        if !authors.is_empty() {
+            return authors;
+        }
+
+        for selector in &site.byline_selectors {
+            let byline_selector = Selector::parse(selector).expect("Unable to construct selector.");
+            let bylines: Vec<String> = doc
+                .select(&byline_selector)
+                .map(|elem| elem.text().collect::<String>())
+                .collect();
+            if !bylines.is_empty() {
+                return bylines;
+            }
+        }
+        Vec::new()
+    }
+
+    // Falls back to the first path segment after /news/, or the first one at
+    // all, e.g. "canada" for cbc.ca/news/canada/...
+    fn section(&self, _doc: &Html, _site: &NewsSite, href: &str) -> Option<String> {
+        let url = Url::parse(href).ok()?;
+        let mut segments = url.path_segments()?.filter(|segment| !segment.is_empty());
+        let first = segments.next()?;
+        let section = if first == "news" {
+            segments.next()?
+        } else {
+            first
+        };
+        // a lone segment is the article slug itself
+        segments.next()?;
+        Some(section.replace('-', " "))
+    }
+
+    fn subtitle(&self, doc: &Html, _site: &NewsSite) -> Option<String> {
+        meta_content(doc, "meta[name=\"description\"]")
+    }
+
+    fn updated(&self, doc: &Html, _site: &NewsSite) -> Option<DateTime<FixedOffset>> {
+        let modified = meta_content(doc, "meta[property=\"article:modified_time\"]")?;
+        parse_pub_date(&modified, dates::now()).ok()
+    }
+
+    // May be relative; callers resolve it against the page URL.
+    fn canonical_url(&self, doc: &Html, _site: &NewsSite) -> Option<String> {
+        let canonical_selector =
+            Selector::parse("link[rel=\"canonical\"]").expect("Unable to construct selector.");
+        doc.select(&canonical_selector)
+            .find_map(|elem| elem.value().attr("href"))
+            .map(String::from)
+            .or_else(|| meta_content(doc, "meta[property=\"og:url\"]"))
+    }
+
+    fn language(&self, doc: &Html, _site: &NewsSite) -> Option<String> {
+        let html_selector = Selector::parse("html").expect("Unable to construct selector.");
+        doc.select(&html_selector)
+            .find_map(|elem| elem.value().attr("lang"))
+            .map(|lang| lang.trim().to_lowercase())
+            .filter(|lang| !lang.is_empty())
+    }
+
+    fn links(&self, doc: &Html, site: &NewsSite) -> Vec<String> {
+        let link_selector = Selector::parse(&site.a_attr).expect("Couldn't construct a selector.");
+        let base = format!("https://{}/", site.link_prefix);
+
+        doc.select(&link_selector)
+            .filter_map(|elem| {
+                let link_text = elem.value().attr("href")?;
+                if link_text.contains("/news/") {
+                    resolve_link(&base, link_text)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+// Splits raw bylines like "By Jane Doe and John Roe, The Canadian Press" into
+// one entry per author or credit, dropping repeats.
+pub fn split_bylines(bylines: &[String]) -> Vec<String> {
+    let mut authors: Vec<String> = Vec::new();
+    for byline in bylines {
+        let byline = byline.split_whitespace().collect::<Vec<_>>().join(" ");
+        let byline = byline
+            .strip_prefix("By ")
+            .or_else(|| byline.strip_prefix("by "))
+            .unwrap_or(&byline);
+        let names = byline
+            .split([',', '&', '|', ';'])
+            .flat_map(|part| part.split(" and "))
+            .map(|name| name.trim().trim_start_matches("By ").trim())
+            .filter(|name| !name.is_empty());
+        for name in names {
+            if !authors
+                .iter()
+                .any(|author| author.eq_ignore_ascii_case(name))
+            {
+                authors.push(name.to_string());
+            }
+        }
+    }
+    authors
+}
+
+fn meta_content(doc: &Html, selector: &str) -> Option<String> {
+    let meta_selector = Selector::parse(selector).expect("Unable to construct selector.");
+    doc.select(&meta_selector)
+        .find_map(|elem| elem.value().attr("content"))
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty())
+}
+
+// Trimmed, non-empty text of every element matching `selector`.
+fn element_texts(doc: &Html, selector: &str) -> Vec<String> {
+    let text_selector = Selector::parse(selector).expect("Unable to construct selector.");
+    doc.select(&text_selector)
+        .map(|elem| elem.text().collect::<Vec<_>>().join(" "))
+        .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|text| !text.is_empty())
+        .collect()
+}
+
+fn date_from_elem(elem: ElementRef) -> Option<DateTime<FixedOffset>> {
+    // prefer machine-readable timestamps over display text
+    if let Some(date) = elem
+        .value()
+        .attr("datetime")
+        .and_then(|datetime| parse_pub_date(datetime, dates::now()).ok())
+    {
+        return Some(date);
+    }
+    let text = elem.text().collect::<String>();
+    parse_pub_date(&text, dates::now()).ok()
+}
+
+fn get_date(doc: &Html, date_selectors: &[String]) -> Result<DateTime<FixedOffset>> {
+    for selector in date_selectors {
+        let date_selector = Selector::parse(selector).expect("Unable to create selector");
+        if let Some(date) = doc.select(&date_selector).find_map(date_from_elem) {
+            return Ok(date);
+        }
+    }
+
+    Err(ScrapeError::MissingDate.into())
+}
+
+fn get_story_div<'a>(doc: &'a Html, story_attr: &str) -> Result<ElementRef<'a>> {
+    let story_selector = Selector::parse(story_attr).expect("Unable to construct selector.");
+    let story = doc.select(&story_selector).next();
+
+    match story {
+        Some(story) => Ok(story),
+        None => Err(ScrapeError::MissingBody.into()),
+    }
+}
+
+pub struct GenericExtractor;
+
+impl SiteExtractor for GenericExtractor {}
+
+// cbc.ca suffixes <title> with whichever desk ran the story ("| CBC News",
+// "| CBC Sports", "| CBC Radio"), so the headline is read from the page's
+// h1 instead of relying on `title_suffixes`.
+pub struct CbcExtractor;
+
+impl SiteExtractor for CbcExtractor {
+    fn title(&self, doc: &Html, site: &NewsSite) -> Result<String> {
+        match element_texts(doc, "h1.detailHeadline").into_iter().next() {
+            Some(headline) => Ok(headline),
+            None => GenericExtractor.title(doc, site),
+        }
+    }
+}
+
+// Postmedia bylines read "Author of the article:" followed by the linked
+// names, so only the links are taken.
+pub struct NationalPostExtractor;
+
+impl SiteExtractor for NationalPostExtractor {
+    fn authors(&self, doc: &Html, site: &NewsSite) -> Vec<String> {
+        let authors = element_texts(doc, ".published-by__author a");
+        if authors.is_empty() {
+            GenericExtractor.authors(doc, site)
+        } else {
+            authors
+        }
+    }
+}
+
+// thestar.com runs the reporter's role on into the byline ("Jane Doe Staff
+// Reporter"), so only the name element is taken.
+pub struct StarExtractor;
+
+impl SiteExtractor for StarExtractor {
+    fn authors(&self, doc: &Html, site: &NewsSite) -> Vec<String> {
+        let authors = element_texts(doc, ".article__byline .article__author-name");
+        if authors.is_empty() {
+            GenericExtractor.authors(doc, site)
+        } else {
+            authors
+        }
+    }
+}
+
+pub struct ExtractorRegistry {
+    extractors: HashMap<String, Box<dyn SiteExtractor>>,
+    fallback: GenericExtractor,
+}
+
+impl ExtractorRegistry {
+    pub fn new() -> Self {
+        let mut registry = ExtractorRegistry {
+            extractors: HashMap::new(),
+            fallback: GenericExtractor,
+        };
+        registry.register("cbc", Box::new(CbcExtractor));
+        registry.register("national_post", Box::new(NationalPostExtractor));
+        registry.register("the_star", Box::new(StarExtractor));
+        registry
+    }
+
+    pub fn register(&mut self, site_name: &str, extractor: Box<dyn SiteExtractor>) {
+        self.extractors.insert(String::from(site_name), extractor);
+    }
+
+    // sites without a dedicated extractor use the config-driven defaults
+    pub fn get(&self, site_name: &str) -> &dyn SiteExtractor {
+        match self.extractors.get(site_name) {
+            Some(extractor) => extractor.as_ref(),
+            None => &self.fallback,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedTitle;
+
+    impl SiteExtractor for FixedTitle {
+        fn title(&self, _doc: &Html, _site: &NewsSite) -> Result<String> {
+            Ok("fixed".into())
+        }
+    }
+
+    fn site(name: &str) -> NewsSite {
+        toml::from_str(&format!(
+            "name = \"{name}\"\na_attr = \"a\"\nnews_href = \"https://example.com/news\"\n\
+             link_prefix = \"example.com\""
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn registry_falls_back_to_the_config_driven_extractor() {
+        let mut registry = ExtractorRegistry::new();
+        registry.register("quirky", Box::new(FixedTitle));
+        let doc = Html::parse_document("<html><head><title>Story</title></head></html>");
+        let quirky = site("quirky");
+        let other = site("other");
+        assert_eq!(
+            registry.get("quirky").title(&doc, &quirky).unwrap(),
+            "fixed"
+        );
+        assert_eq!(registry.get("other").title(&doc, &other).unwrap(), "Story");
+    }
+
+    #[test]
+    fn cbc_reads_the_headline_rather_than_the_desk_title() {
+        let site = site("cbc");
+        let registry = ExtractorRegistry::new();
+        let doc = Html::parse_document(
+            "<html><head><title>Leafs win in overtime | CBC Sports</title></head>\
+             <body><h1 class=\"detailHeadline\">Leafs win in overtime</h1></body></html>",
+        );
+        assert_eq!(
+            registry.get("cbc").title(&doc, &site).unwrap(),
+            "Leafs win in overtime"
+        );
+        let doc = Html::parse_document("<html><head><title>Story | CBC News</title></head></html>");
+        assert_eq!(
+            registry.get("cbc").title(&doc, &site).unwrap(),
+            "Story | CBC News"
+        );
+    }
+
+    #[test]
+    fn national_post_skips_the_byline_label() {
+        let site = site("national_post");
+        let doc = Html::parse_document(
+            "<div class=\"published-by__author\">Author of the article: \
+             <a href=\"/author/jdoe\">Jane Doe</a> and <a href=\"/author/jroe\">John Roe</a></div>",
+        );
+        assert_eq!(
+            ExtractorRegistry::new()
+                .get("national_post")
+                .authors(&doc, &site),
+            ["Jane Doe", "John Roe"]
+        );
+    }
+
+    #[test]
+    fn star_drops_the_reporter_role() {
+        let site = site("the_star");
+        let doc = Html::parse_document(
+            "<div class=\"article__byline\"><span class=\"article__author-name\">Jane Doe</span>\
+             <span class=\"article__author-title\">Staff Reporter</span></div>",
+        );
+        assert_eq!(
+            ExtractorRegistry::new()
+                .get("the_star")
+                .authors(&doc, &site),
+            ["Jane Doe"]
+        );
+        // pages without the byline block fall back to the configured selectors
+        let doc = Html::parse_document("<span class=\"byline\">By John Roe</span>");
+        assert_eq!(
+            ExtractorRegistry::new()
+                .get("the_star")
+                .authors(&doc, &site),
+            ["By John Roe"]
+        );
+    }
+
+    #[test]
+    fn default_date_selectors_cover_each_outlet() {
+        let site = site("any");
+        let extractor = GenericExtractor;
+        for html in [
+            r#"<time class="timeStamp" datetime="2023-03-06T17:00:00.000Z">March 6</time>"#,
+            r#"<span class="published-date__since">March 6, 2023</span>"#,
+            r#"<span class="article__published-date">Mon., March 6, 2023</span>"#,
+        ] {
+            let doc = Html::parse_document(html);
+            let date = extractor.date(&doc, &site).unwrap();
+            assert_eq!(date.date_naive().to_string(), "2023-03-06", "{html}");
+        }
+    }
+}
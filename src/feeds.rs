@@ -0,0 +1,200 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset};
+use roxmltree::{Document, Node};
+
+use crate::discovery::{DiscoveredLink, LinkSource};
+use crate::xml::{child, child_date, child_text};
+
+fn first_date(node: Node, names: &[&str]) -> Option<DateTime<FixedOffset>> {
+    names.iter().find_map(|name| child_date(node, name))
+}
+
+// RSS 2.0: <rss><channel><item> with <link>, <guid>, <pubDate> (or <dc:date>)
+fn parse_rss_item(item: Node) -> Option<DiscoveredLink> {
+    let guid = child_text(item, "guid");
+    let url = child_text(item, "link").or_else(|| {
+        // a permalink guid is the article URL
+        let permalink = child(item, "guid")?.attribute("isPermaLink") != Some("false");
+        guid.clone()
+            .filter(|guid| permalink && guid.starts_with("http"))
+    })?;
+
+    Some(DiscoveredLink {
+        url,
+        source: LinkSource::Feed,
+        title: child_text(item, "title"),
+        published: first_date(item, &["pubDate", "date"]),
+        // <atom:updated> or <dcterms:modified>, which few feeds carry
+        updated: first_date(item, &["updated", "modified"]),
+        guid,
+    })
+}
+
+// Atom: <feed><entry> with <link rel="alternate" href=...>, <id>, <published>/<updated>
+fn parse_atom_entry(entry: Node) -> Option<DiscoveredLink> {
+    let url = entry
+        .children()
+        .filter(|child| child.is_element() && child.tag_name().name() == "link")
+        .find(|link| matches!(link.attribute("rel"), None | Some("alternate")))
+        .and_then(|link| link.attribute("href"))?;
+
+    Some(DiscoveredLink {
+        url: String::from(url),
+        source: LinkSource::Feed,
+        title: child_text(entry, "title"),
+        published: first_date(entry, &["published", "updated"]),
+        updated: child_date(entry, "updated"),
+        guid: child_text(entry, "id"),
+    })
+}
+
+pub fn parse_feed(xml: &str) -> Result<Vec<DiscoveredLink>> {
+    let doc = Document::parse(xml)?;
+    let root = doc.root_element();
+
+    let links = match root.tag_name().name() {
+        "rss" | "RDF" => root
+            .descendants()
+            .filter(|node| node.is_element() && node.tag_name().name() == "item")
+            .filter_map(parse_rss_item)
+            .collect(),
+        "feed" => root
+            .children()
+            .filter(|node| node.is_element() && node.tag_name().name() == "entry")
+            .filter_map(parse_atom_entry)
+            .collect(),
+        other => return Err(anyhow!("Unknown feed format <{other}>.")),
+    };
+
+    Ok(links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(link: &DiscoveredLink) -> Option<String> {
+        link.published.map(|date| date.to_rfc3339())
+    }
+
+    #[test]
+    fn reads_rss_items() {
+        let links = parse_feed(
+            r#"<rss version="2.0"><channel>
+              <item>
+                <title>Transit workers walk off the job</title>
+                <link>https://www.cbc.ca/news/canada/transit-strike-1.1</link>
+                <guid isPermaLink="false">1.1</guid>
+                <pubDate>Fri, 03 Mar 2023 09:15:00 -0500</pubDate>
+              </item>
+            </channel></rss>"#,
+        )
+        .unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(
+            links[0].url,
+            "https://www.cbc.ca/news/canada/transit-strike-1.1"
+        );
+        assert_eq!(links[0].source, LinkSource::Feed);
+        assert_eq!(
+            links[0].title.as_deref(),
+            Some("Transit workers walk off the job")
+        );
+        assert_eq!(links[0].guid.as_deref(), Some("1.1"));
+        assert_eq!(
+            date(&links[0]).as_deref(),
+            Some("2023-03-03T09:15:00-05:00")
+        );
+    }
+
+    #[test]
+    fn a_permalink_guid_stands_in_for_a_missing_link() {
+        let links = parse_feed(
+            r#"<rss version="2.0"><channel>
+              <item><guid>https://example.com/news/permalink</guid></item>
+              <item><guid isPermaLink="true">https://example.com/news/explicit</guid></item>
+              <item><guid isPermaLink="false">https://example.com/news/opaque</guid></item>
+              <item><guid>tag:example.com,2023:42</guid></item>
+            </channel></rss>"#,
+        )
+        .unwrap();
+        let urls: Vec<&str> = links.iter().map(|link| link.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://example.com/news/permalink",
+                "https://example.com/news/explicit"
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_dc_date() {
+        let links = parse_feed(
+            r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"
+                        xmlns="http://purl.org/rss/1.0/"
+                        xmlns:dc="http://purl.org/dc/elements/1.1/">
+              <item>
+                <link>https://example.com/news/story</link>
+                <dc:date>2023-03-06T12:00:00+01:00</dc:date>
+              </item>
+            </rdf:RDF>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            date(&links[0]).as_deref(),
+            Some("2023-03-06T12:00:00+01:00")
+        );
+    }
+
+    #[test]
+    fn reads_atom_entries() {
+        let links = parse_feed(
+            r#"<feed xmlns="http://www.w3.org/2005/Atom">
+              <title>Example</title>
+              <link rel="self" href="https://example.com/feed.atom"/>
+              <entry>
+                <title>Council passes budget</title>
+                <id>tag:example.com,2023:budget</id>
+                <link rel="enclosure" href="https://example.com/budget.mp3"/>
+                <link rel="alternate" href="https://example.com/news/budget"/>
+                <published>2023-03-02T18:40:00-05:00</published>
+                <updated>2023-03-03T08:00:00-05:00</updated>
+              </entry>
+              <entry>
+                <title>Undated</title>
+                <link href="https://example.com/news/undated"/>
+                <updated>2023-03-04T08:00:00Z</updated>
+              </entry>
+            </feed>"#,
+        )
+        .unwrap();
+        assert_eq!(links.len(), 2);
+        // only the alternate link is the article, not the feed's or the enclosure
+        assert_eq!(links[0].url, "https://example.com/news/budget");
+        assert_eq!(
+            links[0].guid.as_deref(),
+            Some("tag:example.com,2023:budget")
+        );
+        assert_eq!(
+            date(&links[0]).as_deref(),
+            Some("2023-03-02T18:40:00-05:00")
+        );
+        assert_eq!(
+            links[0].updated.map(|date| date.to_rfc3339()).as_deref(),
+            Some("2023-03-03T08:00:00-05:00")
+        );
+        // a link without rel is an alternate, and <updated> stands in for <published>
+        assert_eq!(links[1].url, "https://example.com/news/undated");
+        assert_eq!(
+            date(&links[1]).as_deref(),
+            Some("2023-03-04T08:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(parse_feed("<html><body/></html>").is_err());
+        assert!(parse_feed("not xml").is_err());
+    }
+}
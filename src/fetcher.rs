@@ -0,0 +1,475 @@
+use std::{collections::HashMap, io::Read, path::Path, sync::Arc, sync::Mutex, time::Duration};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use rand::Rng;
+use reqwest::{
+    header::{CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER},
+    redirect, Client, RequestBuilder, Response, StatusCode, Url,
+};
+use tokio::{sync::Semaphore, time::sleep};
+
+use crate::errors::ScrapeError;
+use crate::fixtures::FixtureStore;
+use crate::politeness::{host_key, Politeness};
+use crate::response_cache::{CacheEntry, ResponseCache};
+use crate::site_config::{CrawlerConfig, FetchModeConfig};
+
+pub struct Fetcher {
+    client: Client,
+    politeness: Politeness,
+    max_retries: u32,
+    backoff_base: Duration,
+    backoff_max: Duration,
+    global_limit: Semaphore,
+    max_per_host: usize,
+    host_limits: Mutex<HashMap<String, Arc<Semaphore>>>,
+    cache: Option<ResponseCache>,
+    mode: FetchMode,
+}
+
+// Live fetches from the network. Record also saves every page as a fixture,
+// and Replay serves pages from recorded fixtures without touching the network.
+// robots.txt goes through the same fixtures, see `Politeness::rules_for`.
+#[derive(Clone)]
+pub enum FetchMode {
+    Live,
+    Record(Arc<FixtureStore>),
+    Replay(Arc<FixtureStore>),
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// Retry-After is either a number of seconds or an HTTP date.
+fn retry_after(res: &Response) -> Option<Duration> {
+    let value = res.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+// Sitemaps are often served as .xml.gz files rather than with a gzip
+// Content-Encoding, so reqwest hands them over still compressed.
+fn gunzip(bytes: &[u8]) -> Result<String> {
+    // some servers decompress on the fly despite the .gz name
+    if !bytes.starts_with(&[0x1f, 0x8b]) {
+        return Ok(String::from_utf8_lossy(bytes).into_owned());
+    }
+    let mut text = String::new();
+    GzDecoder::new(bytes).read_to_string(&mut text)?;
+    Ok(text)
+}
+
+// Adds validators from a cached response so the server can answer 304 Not Modified.
+fn conditional(request: RequestBuilder, cached: Option<&CacheEntry>) -> RequestBuilder {
+    let Some(cached) = cached else {
+        return request;
+    };
+    let mut request = request;
+    if let Some(etag) = &cached.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &cached.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+    request
+}
+
+impl Fetcher {
+    pub fn new(config: &CrawlerConfig) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent(&config.user_agent)
+            .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+            .timeout(Duration::from_millis(config.read_timeout_ms))
+            .redirect(redirect::Policy::limited(config.max_redirects))
+            .build()?;
+        let cache = if config.cache {
+            Some(ResponseCache::new(Path::new(&config.cache_dir))?)
+        } else {
+            None
+        };
+        let fixtures_dir = Path::new(&config.fixtures_dir);
+        let mode = match config.mode {
+            FetchModeConfig::Live => FetchMode::Live,
+            FetchModeConfig::Record => {
+                FetchMode::Record(Arc::new(FixtureStore::open(fixtures_dir)?))
+            }
+            FetchModeConfig::Replay => {
+                FetchMode::Replay(Arc::new(FixtureStore::open(fixtures_dir)?))
+            }
+        };
+        let politeness = Politeness::new(config, client.clone(), mode.clone());
+
+        Ok(Fetcher {
+            client,
+            politeness,
+            max_retries: config.max_retries,
+            backoff_base: Duration::from_millis(config.backoff_base_ms),
+            backoff_max: Duration::from_millis(config.backoff_max_ms),
+            global_limit: Semaphore::new(config.max_concurrency),
+            max_per_host: config.max_per_host,
+            host_limits: Mutex::new(HashMap::new()),
+            cache,
+            mode,
+        })
+    }
+
+    fn host_limit(&self, href: &str) -> Result<Arc<Semaphore>> {
+        let host = host_key(&Url::parse(href)?)?;
+        let mut host_limits = self.host_limits.lock().unwrap();
+        let limit = host_limits
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_per_host)));
+        Ok(limit.clone())
+    }
+
+    // Exponential backoff with jitter: a random wait between half and all of
+    // base * 2^attempt, capped at backoff_max.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .backoff_base
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.backoff_max);
+        let half = backoff / 2;
+        let jitter_ms = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+        half + Duration::from_millis(jitter_ms)
+    }
+
+    async fn send(&self, href: &str, cached: Option<&CacheEntry>) -> reqwest::Result<Response> {
+        let host_limit = self
+            .host_limit(href)
+            .expect("URL was checked by politeness.");
+        let _host_permit = host_limit
+            .acquire()
+            .await
+            .expect("Semaphore is never closed.");
+        let _global_permit = self
+            .global_limit
+            .acquire()
+            .await
+            .expect("Semaphore is never closed.");
+        conditional(self.client.get(href), cached).send().await
+    }
+
+    async fn store(&self, href: &str, res: Response) -> Result<String> {
+        let header = |name| {
+            res.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let gzipped = href.ends_with(".gz")
+            || header(CONTENT_TYPE).is_some_and(|content_type| content_type.contains("gzip"));
+        let body = if gzipped {
+            gunzip(&res.bytes().await?)?
+        } else {
+            res.text().await?
+        };
+
+        if let Some(cache) = &self.cache {
+            if let Err(err) = cache.store(href, etag, last_modified, &body) {
+                println!("Cannot cache response for {href}: {err}");
+            }
+        }
+        Ok(body)
+    }
+
+    pub async fn sitemaps_for(&self, href: &str) -> Result<Vec<String>> {
+        let rules = self.politeness.rules_for(&Url::parse(href)?).await?;
+        Ok(rules.sitemaps().to_vec())
+    }
+
+    pub async fn get_text(&self, href: &str) -> Result<String> {
+        match &self.mode {
+            FetchMode::Live => self.fetch_live(href).await,
+            FetchMode::Record(fixtures) => {
+                let body = self.fetch_live(href).await?;
+                fixtures.save(href, &body)?;
+                Ok(body)
+            }
+            // replay honours robots.txt like a live run but never waits
+            FetchMode::Replay(fixtures) => {
+                self.politeness.check_allowed(&Url::parse(href)?).await?;
+                fixtures.load(href)
+            }
+        }
+    }
+
+    async fn fetch_live(&self, href: &str) -> Result<String> {
+        let cache = self.cache.as_ref();
+        let cached = cache.and_then(|cache| cache.entry(href));
+
+        let mut attempt = 0;
+        loop {
+            self.politeness.before_request(href).await?;
+
+            let wait = match self.send(href, cached.as_ref()).await {
+                Ok(res) if res.status() == StatusCode::NOT_MODIFIED => {
+                    if let (Some(cache), Some(cached)) = (cache, &cached) {
+                        return cache.body(cached);
+                    }
+                    // we never send validators without a cached copy
+                    return Err(ScrapeError::HttpStatus(304).into());
+                }
+                Ok(res) if res.status().is_success() => return self.store(href, res).await,
+                Ok(res) if is_retryable(res.status()) && attempt < self.max_retries => {
+                    let server_wait = retry_after(&res).unwrap_or_default();
+                    // a server asking us to come back later than backoff_max
+                    // is taken as a refusal rather than stalling the task
+                    if server_wait > self.backoff_max {
+                        return Err(ScrapeError::HttpStatus(res.status().as_u16()).into());
+                    }
+                    server_wait.max(self.backoff(attempt))
+                }
+                Ok(res) => return Err(ScrapeError::HttpStatus(res.status().as_u16()).into()),
+                Err(err)
+                    if (err.is_timeout() || err.is_connect()) && attempt < self.max_retries =>
+                {
+                    self.backoff(attempt)
+                }
+                Err(err) => return Err(ScrapeError::Network(err.to_string()).into()),
+            };
+
+            attempt += 1;
+            println!(
+                "Retrying {href} in {}ms (attempt {attempt})",
+                wait.as_millis()
+            );
+            sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    // Answers each request with `respond(path, hits on that path so far)`,
+    // closing the connection after every response.
+    fn mock_server<F>(respond: F) -> (String, Arc<Mutex<HashMap<String, usize>>>)
+    where
+        F: Fn(&str, usize) -> String + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(Mutex::new(HashMap::new()));
+        let server_hits = hits.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+                let path = request_line
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or("/")
+                    .to_string();
+                let n = {
+                    let mut hits = server_hits.lock().unwrap();
+                    let count = hits.entry(path.clone()).or_insert(0);
+                    *count += 1;
+                    *count - 1
+                };
+                let _ = stream.write_all(respond(&path, n).as_bytes());
+            }
+        });
+        (base, hits)
+    }
+
+    fn response(status: &str, headers: &[&str], body: &str) -> String {
+        let mut response = format!("HTTP/1.1 {status}\r\nConnection: close\r\n");
+        for header in headers {
+            response.push_str(&format!("{header}\r\n"));
+        }
+        response.push_str(&format!("Content-Length: {}\r\n\r\n{body}", body.len()));
+        response
+    }
+
+    fn not_found() -> String {
+        response("404 Not Found", &[], "")
+    }
+
+    fn test_fetcher() -> Fetcher {
+        let config = CrawlerConfig {
+            min_delay_ms: 0,
+            backoff_base_ms: 1,
+            backoff_max_ms: 50,
+            max_retries: 2,
+            cache: false,
+            ..CrawlerConfig::default()
+        };
+        Fetcher::new(&config).unwrap()
+    }
+
+    fn fixture_fetcher(mode: FetchModeConfig, fixtures_dir: &Path) -> Fetcher {
+        let config = CrawlerConfig {
+            min_delay_ms: 0,
+            cache: false,
+            mode,
+            fixtures_dir: fixtures_dir.to_string_lossy().into_owned(),
+            ..CrawlerConfig::default()
+        };
+        Fetcher::new(&config).unwrap()
+    }
+
+    fn hits(hits: &Arc<Mutex<HashMap<String, usize>>>, path: &str) -> usize {
+        hits.lock().unwrap().get(path).copied().unwrap_or(0)
+    }
+
+    fn get_text(fetcher: &Fetcher, href: &str) -> Result<String> {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(fetcher.get_text(href))
+    }
+
+    fn status(err: &anyhow::Error) -> Option<u16> {
+        match err.downcast_ref::<ScrapeError>() {
+            Some(ScrapeError::HttpStatus(status)) => Some(*status),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn unzips_gzipped_sitemaps() {
+        let sitemap = "<urlset><url><loc>https://example.com/news/story</loc></url></urlset>";
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(sitemap.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        assert_eq!(gunzip(&gzipped).unwrap(), sitemap);
+        // a .gz URL the server already decompressed comes through as is
+        assert_eq!(gunzip(sitemap.as_bytes()).unwrap(), sitemap);
+        // a truncated archive is an error rather than garbage
+        assert!(gunzip(&gzipped[..gzipped.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn retries_server_errors_then_succeeds() {
+        let (base, server_hits) = mock_server(|path, n| match (path, n) {
+            ("/story", 0) => response("503 Service Unavailable", &["Retry-After: 0"], ""),
+            ("/story", _) => response("200 OK", &[], "story text"),
+            _ => not_found(),
+        });
+        let text = get_text(&test_fetcher(), &format!("{base}/story")).unwrap();
+        assert_eq!(text, "story text");
+        assert_eq!(hits(&server_hits, "/story"), 2);
+    }
+
+    #[test]
+    fn gives_up_after_max_retries() {
+        let (base, server_hits) = mock_server(|path, _| match path {
+            "/story" => response("500 Internal Server Error", &[], ""),
+            _ => not_found(),
+        });
+        let err = get_text(&test_fetcher(), &format!("{base}/story")).unwrap_err();
+        assert_eq!(status(&err), Some(500));
+        assert_eq!(hits(&server_hits, "/story"), 3);
+    }
+
+    #[test]
+    fn refuses_to_wait_longer_than_backoff_max() {
+        let (base, server_hits) = mock_server(|path, _| match path {
+            "/story" => response("429 Too Many Requests", &["Retry-After: 86400"], ""),
+            _ => not_found(),
+        });
+        let started = std::time::Instant::now();
+        let err = get_text(&test_fetcher(), &format!("{base}/story")).unwrap_err();
+        assert_eq!(status(&err), Some(429));
+        assert_eq!(hits(&server_hits, "/story"), 1);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn does_not_retry_client_errors() {
+        let (base, server_hits) = mock_server(|_, _| not_found());
+        let err = get_text(&test_fetcher(), &format!("{base}/missing")).unwrap_err();
+        assert_eq!(status(&err), Some(404));
+        assert_eq!(hits(&server_hits, "/missing"), 1);
+    }
+
+    #[test]
+    fn honours_robots_txt() {
+        let (base, server_hits) = mock_server(|path, _| match path {
+            "/robots.txt" => response("200 OK", &[], "User-agent: *\nDisallow: /private/\n"),
+            _ => response("200 OK", &[], "page"),
+        });
+        let fetcher = test_fetcher();
+        let err = get_text(&fetcher, &format!("{base}/private/story")).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ScrapeError>(),
+            Some(ScrapeError::RobotsDisallowed)
+        ));
+        assert_eq!(hits(&server_hits, "/private/story"), 0);
+        assert_eq!(
+            get_text(&fetcher, &format!("{base}/news/story")).unwrap(),
+            "page"
+        );
+    }
+
+    #[test]
+    fn records_and_replays_robots_txt() {
+        let (base, server_hits) = mock_server(|path, _| match path {
+            "/robots.txt" => response(
+                "200 OK",
+                &[],
+                "User-agent: *\nDisallow: /private/\nSitemap: https://example.com/news.xml\n",
+            ),
+            _ => response("200 OK", &[], "page"),
+        });
+        let fixtures = tempfile::tempdir().unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let recorder = fixture_fetcher(FetchModeConfig::Record, fixtures.path());
+        runtime
+            .block_on(recorder.get_text(&format!("{base}/news/story")))
+            .unwrap();
+        assert_eq!(hits(&server_hits, "/robots.txt"), 1);
+
+        let replayer = fixture_fetcher(FetchModeConfig::Replay, fixtures.path());
+        let sitemaps = runtime
+            .block_on(replayer.sitemaps_for(&format!("{base}/news/story")))
+            .unwrap();
+        assert_eq!(sitemaps, ["https://example.com/news.xml"]);
+        assert_eq!(
+            runtime
+                .block_on(replayer.get_text(&format!("{base}/news/story")))
+                .unwrap(),
+            "page"
+        );
+        // replay never goes back to the server
+        assert_eq!(hits(&server_hits, "/robots.txt"), 1);
+        assert_eq!(hits(&server_hits, "/news/story"), 1);
+    }
+
+    #[test]
+    fn replay_fails_without_recorded_robots_txt() {
+        let fixtures = tempfile::tempdir().unwrap();
+        let replayer = fixture_fetcher(FetchModeConfig::Replay, fixtures.path());
+        let err = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(replayer.sitemaps_for("https://www.cbc.ca/news"))
+            .unwrap_err();
+        assert!(err.to_string().contains("robots.txt"));
+    }
+}
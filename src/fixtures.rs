@@ -0,0 +1,68 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::Url;
+
+use crate::response_cache::sha256_hex;
+
+// HTML pages saved to disk so a scrape can be replayed without the network.
+// `index.json` maps each URL to a file under the fixture directory, which keeps
+// fixtures easy to inspect and to hand-edit.
+pub struct FixtureStore {
+    root: PathBuf,
+    index: Mutex<BTreeMap<String, String>>,
+}
+
+impl FixtureStore {
+    pub fn open(root: &Path) -> Result<Self> {
+        let index_path = root.join("index.json");
+        let index = if index_path.exists() {
+            let text = fs::read_to_string(&index_path)
+                .with_context(|| format!("Cannot read fixture index {}", index_path.display()))?;
+            serde_json::from_str(&text)
+                .with_context(|| format!("Invalid fixture index {}", index_path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(FixtureStore {
+            root: root.to_path_buf(),
+            index: Mutex::new(index),
+        })
+    }
+
+    pub fn load(&self, url: &str) -> Result<String> {
+        let index = self.index.lock().unwrap();
+        let file = index
+            .get(url)
+            .ok_or(anyhow!("No fixture recorded for {url}"))?;
+        let path = self.root.join(file);
+        fs::read_to_string(&path).with_context(|| format!("Cannot read fixture {}", path.display()))
+    }
+
+    pub fn save(&self, url: &str, body: &str) -> Result<()> {
+        let host = Url::parse(url)?
+            .host_str()
+            .ok_or(anyhow!("URL {url} has no host."))?
+            .to_string();
+        let file = format!("{host}/{}.html", &sha256_hex(url.as_bytes())[..16]);
+
+        let path = self.root.join(&file);
+        fs::create_dir_all(path.parent().expect("Fixture path has a host directory."))?;
+        fs::write(&path, body)?;
+
+        // the index is rewritten under the lock so concurrent saves don't drop entries
+        let mut index = self.index.lock().unwrap();
+        index.insert(String::from(url), file);
+        fs::write(
+            self.root.join("index.json"),
+            serde_json::to_string_pretty(&*index)?,
+        )?;
+        Ok(())
+    }
+}
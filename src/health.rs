@@ -0,0 +1,381 @@
+use std::{collections::HashSet, path::Path};
+
+use anyhow::{anyhow, Result};
+use scraper::{Html, Selector};
+
+use crate::crawl_state::CrawlState;
+use crate::discovery::normalize_url;
+use crate::extractors::{ExtractorRegistry, SiteExtractor};
+use crate::fetcher::Fetcher;
+use crate::scrape_data::get_news_links;
+use crate::site_config::{load_config, NewsSite};
+use crate::sitemaps::DateRange;
+use crate::text::TextNormalizer;
+
+// How many earlier healthy checks a site's baseline is averaged over.
+const BASELINE_CHECKS: usize = 5;
+
+// Selector hits on a site's section page and on a sample of its articles.
+#[derive(Debug, Clone, Default)]
+pub struct SelectorHealth {
+    // article links found on `news_href`, None when HTML discovery is off
+    pub links: Option<usize>,
+    pub sampled: usize,
+    pub title: usize,
+    pub date: usize,
+    // None for sites without a `body` selector
+    pub body: Option<usize>,
+    pub paragraphs: usize,
+}
+
+impl SelectorHealth {
+    fn rate(&self, hits: usize) -> Option<f64> {
+        (self.sampled > 0).then(|| hits as f64 / self.sampled as f64)
+    }
+
+    // (selector, hit rate) for each per-article selector that was checked.
+    pub fn rates(&self) -> Vec<(&'static str, Option<f64>)> {
+        let mut rates = vec![
+            ("title", self.rate(self.title)),
+            ("date", self.rate(self.date)),
+        ];
+        if let Some(body) = self.body {
+            rates.push(("body", self.rate(body)));
+        }
+        rates.push(("paragraphs", self.rate(self.paragraphs)));
+        rates
+    }
+}
+
+// Averages over a site's recent healthy checks, as stored in the crawl state.
+#[derive(Debug, Clone, Default)]
+pub struct SelectorBaseline {
+    pub checks: usize,
+    pub links: Option<f64>,
+    pub title: Option<f64>,
+    pub date: Option<f64>,
+    pub body: Option<f64>,
+    pub paragraphs: Option<f64>,
+}
+
+impl SelectorBaseline {
+    pub fn rate(&self, selector: &str) -> Option<f64> {
+        match selector {
+            "title" => self.title,
+            "date" => self.date,
+            "body" => self.body,
+            "paragraphs" => self.paragraphs,
+            _ => None,
+        }
+    }
+}
+
+fn percent(rate: f64) -> String {
+    format!("{:.0}%", rate * 100.0)
+}
+
+// Without a baseline only selectors that match nothing at all are flagged.
+// With one, a hit rate that drops by more than `threshold`, or a link count
+// that drops by more than that fraction, marks the site as degraded.
+pub fn degradations(
+    health: &SelectorHealth,
+    baseline: Option<&SelectorBaseline>,
+    threshold: f64,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    match (health.links, baseline.and_then(|baseline| baseline.links)) {
+        (Some(0), _) => problems.push("links: no article links on news_href".to_string()),
+        (Some(links), Some(expected)) if (links as f64) < expected * (1.0 - threshold) => {
+            problems.push(format!("links: fell from {expected:.0} to {links}"))
+        }
+        _ => (),
+    }
+
+    for (selector, rate) in health.rates() {
+        let Some(rate) = rate else {
+            continue;
+        };
+        match baseline.and_then(|baseline| baseline.rate(selector)) {
+            Some(expected) if expected - rate > threshold => problems.push(format!(
+                "{selector}: hit rate fell from {} to {}",
+                percent(expected),
+                percent(rate)
+            )),
+            None if rate == 0.0 => problems.push(format!(
+                "{selector}: matched none of {} sampled pages",
+                health.sampled
+            )),
+            _ => (),
+        }
+    }
+    problems
+}
+
+fn check_page(
+    text: &str,
+    site: &NewsSite,
+    extractor: &dyn SiteExtractor,
+    normalizer: &TextNormalizer,
+    health: &mut SelectorHealth,
+) -> Result<()> {
+    let doc = Html::parse_document(text);
+    health.sampled += 1;
+    if extractor.title(&doc, site).is_ok() {
+        health.title += 1;
+    }
+    if extractor.date(&doc, site).is_ok() {
+        health.date += 1;
+    }
+    if let (Some(body), Some(hits)) = (&site.body, health.body.as_mut()) {
+        let body_selector = Selector::parse(body)
+            .map_err(|err| anyhow!("invalid `body` selector ({body:?}): {err:?}"))?;
+        if doc.select(&body_selector).next().is_some() {
+            *hits += 1;
+        }
+    }
+    if extractor
+        .paragraphs(&doc, site, normalizer)
+        .is_ok_and(|paragraphs| !paragraphs.is_empty())
+    {
+        health.paragraphs += 1;
+    }
+    Ok(())
+}
+
+// Reads the section page and up to `sample` of the articles it links to.
+// Sites without HTML discovery are sampled from their feeds and sitemaps.
+async fn check_site(
+    site: &NewsSite,
+    extractor: &dyn SiteExtractor,
+    fetcher: &Fetcher,
+    backfill: &DateRange,
+    sample: usize,
+) -> Result<SelectorHealth> {
+    let mut health = SelectorHealth {
+        body: site.body.as_ref().map(|_| 0),
+        ..SelectorHealth::default()
+    };
+
+    let links = if site.html_discovery {
+        let text = fetcher.get_text(&site.news_href).await?;
+        let links = extractor.links(&Html::parse_document(&text), site);
+        health.links = Some(links.len());
+        links
+    } else {
+        // failing sources are printed as they are found
+        get_news_links(site, extractor, fetcher, backfill, &mut Vec::new())
+            .await?
+            .into_iter()
+            .map(|link| link.url)
+            .collect()
+    };
+
+    let mut seen = HashSet::new();
+    let normalizer = TextNormalizer::new(&site.text);
+    for href in links {
+        if health.sampled >= sample {
+            break;
+        }
+        if !seen.insert(normalize_url(&href).unwrap_or_else(|| href.clone())) {
+            continue;
+        }
+        match fetcher.get_text(&href).await {
+            Ok(text) => check_page(&text, site, extractor, &normalizer, &mut health)?,
+            Err(err) => println!("Cannot fetch sample article {href}: {err}"),
+        }
+    }
+    Ok(health)
+}
+
+fn print_health(site: &NewsSite, health: &SelectorHealth, baseline: Option<&SelectorBaseline>) {
+    match baseline {
+        Some(baseline) => println!(
+            "{}: {} pages sampled, baseline from {} earlier checks",
+            site.name, health.sampled, baseline.checks
+        ),
+        None => println!(
+            "{}: {} pages sampled, no baseline yet",
+            site.name, health.sampled
+        ),
+    }
+    if let Some(links) = health.links {
+        match baseline.and_then(|baseline| baseline.links) {
+            Some(expected) => println!("  links       {links} (baseline {expected:.0})"),
+            None => println!("  links       {links}"),
+        }
+    }
+    for (selector, rate) in health.rates() {
+        let Some(rate) = rate else {
+            continue;
+        };
+        match baseline.and_then(|baseline| baseline.rate(selector)) {
+            Some(expected) => println!(
+                "  {selector:<11} {} (baseline {})",
+                percent(rate),
+                percent(expected)
+            ),
+            None => println!("  {selector:<11} {}", percent(rate)),
+        }
+    }
+}
+
+// Returns false if any site degraded or couldn't be checked.
+async fn check_all(config_path: &Path) -> Result<bool> {
+    let config = load_config(config_path)?;
+    let registry = ExtractorRegistry::new();
+    let fetcher = Fetcher::new(&config.crawler)?;
+    let state = CrawlState::open(Path::new(&config.crawler.state_path))?;
+
+    let mut healthy = true;
+    for site in &config.sites {
+        let extractor = registry.get(&site.name);
+        let health = match check_site(
+            site,
+            extractor,
+            &fetcher,
+            &config.crawler.backfill,
+            config.crawler.check_sample,
+        )
+        .await
+        {
+            Ok(health) => health,
+            Err(err) => {
+                println!("{}: cannot check selectors: {err:#}", site.name);
+                healthy = false;
+                continue;
+            }
+        };
+
+        let baseline = state.selector_baseline(&site.name, BASELINE_CHECKS)?;
+        print_health(site, &health, baseline.as_ref());
+        let problems = degradations(&health, baseline.as_ref(), config.crawler.check_threshold);
+        for problem in &problems {
+            println!("  DEGRADED {problem}");
+        }
+        // degraded checks are kept for the record but never become the baseline
+        state.record_selector_check(&site.name, &health, !problems.is_empty())?;
+        healthy &= problems.is_empty();
+    }
+    Ok(healthy)
+}
+
+pub fn check_sites(config_path: &Path) -> Result<bool> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(check_all(config_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extractors::GenericExtractor;
+    use crate::site_config::NewsSite;
+
+    // Five sampled pages with every selector matching on four of them.
+    fn health(links: Option<usize>) -> SelectorHealth {
+        SelectorHealth {
+            links,
+            sampled: 5,
+            title: 4,
+            date: 4,
+            body: Some(4),
+            paragraphs: 4,
+        }
+    }
+
+    fn baseline(links: Option<f64>) -> SelectorBaseline {
+        SelectorBaseline {
+            checks: 3,
+            links,
+            title: Some(0.8),
+            date: Some(0.8),
+            body: Some(0.8),
+            paragraphs: Some(0.8),
+        }
+    }
+
+    #[test]
+    fn zero_links_are_flagged_with_or_without_a_baseline() {
+        let problems = degradations(&health(Some(0)), None, 0.3);
+        assert_eq!(problems, ["links: no article links on news_href"]);
+        let problems = degradations(&health(Some(0)), Some(&baseline(Some(20.0))), 0.3);
+        assert_eq!(problems, ["links: no article links on news_href"]);
+        // without HTML discovery there is no link count to judge
+        assert!(degradations(&health(None), Some(&baseline(Some(20.0))), 0.3).is_empty());
+    }
+
+    #[test]
+    fn flags_link_counts_that_fall_by_more_than_the_threshold() {
+        let baseline = baseline(Some(20.0));
+        assert!(degradations(&health(Some(15)), Some(&baseline), 0.3).is_empty());
+        assert_eq!(
+            degradations(&health(Some(13)), Some(&baseline), 0.3),
+            ["links: fell from 20 to 13"]
+        );
+        // any nonzero count passes without a baseline
+        assert!(degradations(&health(Some(1)), None, 0.3).is_empty());
+    }
+
+    #[test]
+    fn flags_hit_rates_that_fall_by_more_than_the_threshold() {
+        let baseline = baseline(Some(20.0));
+        let mut health = health(Some(20));
+        health.title = 3;
+        assert!(degradations(&health, Some(&baseline), 0.3).is_empty());
+
+        health.title = 2;
+        health.date = 0;
+        assert_eq!(
+            degradations(&health, Some(&baseline), 0.3),
+            [
+                "title: hit rate fell from 80% to 40%",
+                "date: hit rate fell from 80% to 0%"
+            ]
+        );
+    }
+
+    #[test]
+    fn without_a_baseline_only_selectors_matching_nothing_are_flagged() {
+        let mut health = health(Some(20));
+        health.title = 1;
+        health.body = Some(0);
+        assert_eq!(
+            degradations(&health, None, 0.3),
+            ["body: matched none of 5 sampled pages"]
+        );
+        // nothing sampled means no rates to judge
+        let health = SelectorHealth {
+            links: Some(20),
+            ..SelectorHealth::default()
+        };
+        assert!(degradations(&health, None, 0.3).is_empty());
+    }
+
+    #[test]
+    fn an_invalid_body_selector_is_an_error() {
+        let site: NewsSite = toml::from_str(
+            r#"
+            name = "broken"
+            news_href = "https://example.com/news"
+            link_prefix = "example.com"
+            a_attr = "a"
+            body = "div..story"
+            "#,
+        )
+        .unwrap();
+        let normalizer = TextNormalizer::new(&site.text);
+        let mut health = SelectorHealth {
+            body: Some(0),
+            ..SelectorHealth::default()
+        };
+        let err = check_page(
+            "<html></html>",
+            &site,
+            &GenericExtractor,
+            &normalizer,
+            &mut health,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("div..story"), "{err}");
+    }
+}
@@ -0,0 +1,212 @@
+use std::cmp::Reverse;
+
+use serde::{Deserialize, Serialize};
+use whatlang::Lang;
+
+use crate::article::Article;
+
+// ISO 639-1 codes, to match `lang` attributes and the nlprule tokenizer
+// names. Other languages keep whatlang's three-letter code.
+const TWO_LETTER_CODES: [(Lang, &str); 16] = [
+    (Lang::Eng, "en"),
+    (Lang::Fra, "fr"),
+    (Lang::Deu, "de"),
+    (Lang::Spa, "es"),
+    (Lang::Ita, "it"),
+    (Lang::Por, "pt"),
+    (Lang::Nld, "nl"),
+    (Lang::Rus, "ru"),
+    (Lang::Ukr, "uk"),
+    (Lang::Pol, "pl"),
+    (Lang::Cmn, "zh"),
+    (Lang::Jpn, "ja"),
+    (Lang::Kor, "ko"),
+    (Lang::Ara, "ar"),
+    (Lang::Hin, "hi"),
+    (Lang::Tgl, "tl"),
+];
+
+// Paragraphs shorter than this are bylines, captions and credits, which say
+// little about the language of the story.
+const MIN_PARAGRAPH_WORDS: usize = 6;
+// Languages covering less of the text than this are detection noise rather
+// than a second language.
+const MIN_SHARE: f64 = 0.1;
+// Below this, the detected language loses to the page's `lang` attribute.
+const MIN_CONFIDENCE: f64 = 0.5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageShare {
+    pub code: String,
+    pub share: f64,
+}
+
+// The language of an article's text by trigram statistics, worked out offline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectedLanguage {
+    pub code: String,
+    pub confidence: f64,
+    // share of words in each language, paragraph by paragraph, largest first;
+    // more than one entry means a bilingual page
+    pub mix: Vec<LanguageShare>,
+}
+
+fn language_code(lang: Lang) -> String {
+    TWO_LETTER_CODES
+        .iter()
+        .find(|(known, _)| *known == lang)
+        .map_or(lang.code(), |(_, code)| code)
+        .to_string()
+}
+
+// "en-CA" and "en_ca" are both "en".
+pub fn primary_subtag(tag: &str) -> Option<String> {
+    let primary = tag.split(['-', '_']).next()?.trim().to_lowercase();
+    (!primary.is_empty()).then_some(primary)
+}
+
+// Detects each paragraph separately and weights the results by word count, so
+// a bilingual page gets its majority language and a `mix` of both. Pages with
+// only short paragraphs are detected as a whole.
+pub fn detect_language(paragraphs: &[String]) -> Option<DetectedLanguage> {
+    // (code, words, confidence summed over those words)
+    let mut by_code: Vec<(String, usize, f64)> = Vec::new();
+    let mut total = 0;
+    for paragraph in paragraphs {
+        let words = paragraph.split_whitespace().count();
+        if words < MIN_PARAGRAPH_WORDS {
+            continue;
+        }
+        let Some(info) = whatlang::detect(paragraph) else {
+            continue;
+        };
+        let code = language_code(info.lang());
+        let confidence = info.confidence() * words as f64;
+        total += words;
+        match by_code.iter_mut().find(|(known, _, _)| *known == code) {
+            Some((_, count, sum)) => {
+                *count += words;
+                *sum += confidence;
+            }
+            None => by_code.push((code, words, confidence)),
+        }
+    }
+    by_code.sort_by_key(|(_, words, _)| Reverse(*words));
+
+    let (code, confidence) = match by_code.first() {
+        Some((code, words, sum)) => (code.clone(), sum / *words as f64),
+        None => {
+            let info = whatlang::detect(&paragraphs.join("\n"))?;
+            (language_code(info.lang()), info.confidence())
+        }
+    };
+    let mix = by_code
+        .into_iter()
+        .map(|(code, words, _)| LanguageShare {
+            code,
+            share: words as f64 / total as f64,
+        })
+        .filter(|language| language.share >= MIN_SHARE)
+        .collect();
+
+    Some(DetectedLanguage {
+        code,
+        confidence,
+        mix,
+    })
+}
+
+// The detected language when detection is confident, otherwise the page's
+// declared one.
+pub fn article_language(article: &Article) -> Option<String> {
+    article
+        .detected_language
+        .as_ref()
+        .filter(|detected| detected.confidence >= MIN_CONFIDENCE)
+        .map(|detected| detected.code.clone())
+        .or_else(|| article.language.as_deref().and_then(primary_subtag))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const ENGLISH: &str = "City council passed its operating budget late on Thursday night after \
+        a debate that ran for more than nine hours, raising property taxes by just under six \
+        per cent and adding money for road repairs, snow clearing and the transit system.";
+    const FRENCH: &str = "Le conseil municipal a adopté son budget jeudi soir après un long débat.";
+
+    fn paragraphs(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    #[test]
+    fn weights_paragraphs_by_length() {
+        // two French paragraphs are outweighed by one long English one
+        let detected = detect_language(&paragraphs(&[FRENCH, ENGLISH, FRENCH])).unwrap();
+        assert_eq!(detected.code, "en");
+        let mix: Vec<&str> = detected
+            .mix
+            .iter()
+            .map(|share| share.code.as_str())
+            .collect();
+        assert_eq!(mix, ["en", "fr"]);
+        let english_words = ENGLISH.split_whitespace().count() as f64;
+        let french_words = FRENCH.split_whitespace().count() as f64;
+        let expected = english_words / (english_words + 2.0 * french_words);
+        assert!((detected.mix[0].share - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn skips_short_paragraphs_unless_there_is_nothing_else() {
+        let detected = detect_language(&paragraphs(&["Par Jean Tremblay", ENGLISH])).unwrap();
+        assert_eq!(detected.code, "en");
+        assert_eq!(detected.mix.len(), 1);
+        assert_eq!(detected.mix[0].share, 1.0);
+
+        // a page of short paragraphs is detected as a whole, without a mix
+        let detected = detect_language(&paragraphs(&[
+            "Le conseil municipal a adopté",
+            "son budget jeudi soir",
+            "après un très long débat",
+        ]))
+        .unwrap();
+        assert_eq!(detected.code, "fr");
+        assert!(detected.mix.is_empty());
+        assert!(detect_language(&[]).is_none());
+    }
+
+    fn article(lang: Option<&str>, detected: Option<(&str, f64)>) -> Article {
+        let detected = detected
+            .map(|(code, confidence)| json!({ "code": code, "confidence": confidence, "mix": [] }));
+        serde_json::from_value(json!({
+            "url": "https://example.com/news/story",
+            "canonical_url": "https://example.com/news/story",
+            "site": "example",
+            "title": "Story",
+            "authors": [],
+            "published": "2023-03-06T12:00:00Z",
+            "scraped": "2023-03-06T13:00:00Z",
+            "language": lang,
+            "detected_language": detected,
+            "paragraphs": [],
+            "content_hash": "",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn confident_detection_wins_over_the_lang_attribute() {
+        // templates often declare English on every page
+        let english_page = article(Some("en-CA"), Some(("fr", 0.9)));
+        assert_eq!(article_language(&english_page).as_deref(), Some("fr"));
+
+        let unsure = article(Some("en-CA"), Some(("fr", 0.3)));
+        assert_eq!(article_language(&unsure).as_deref(), Some("en"));
+        let undetected = article(Some("FR_ca"), None);
+        assert_eq!(article_language(&undetected).as_deref(), Some("fr"));
+        assert_eq!(article_language(&article(None, Some(("fr", 0.3)))), None);
+    }
+}
@@ -1,25 +1,82 @@
+use std::env;
 use std::path::*;
 
-use crate::preprocess::{load_tokenizer, tokenize_headline_text};
+use crate::preprocess::{load_tokenizers, tokenize_headline_text};
 
+mod article;
+mod crawl_state;
+mod dates;
+mod discovery;
+mod errors;
+mod extractors;
+mod feeds;
+mod fetcher;
+mod fixtures;
+mod health;
+mod language;
+mod metadata;
+mod paywall;
+mod politeness;
 mod prepare_dataset;
 mod preprocess;
+mod readability;
+mod report;
+mod response_cache;
+mod revisions;
 mod scrape_data;
+mod site_config;
+mod sitemaps;
+mod storage;
+mod text;
 mod tf_idf;
+mod wire;
+mod xml;
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("scrape") => {
+            let config_path = args.get(2).map_or("sites.toml", String::as_str);
+            scrape_data::scrape_sites(Path::new(config_path)).expect("Could not scrape sites.");
+        }
+        Some("headline-changes") => {
+            let config_path = args.get(2).map_or("sites.toml", String::as_str);
+            revisions::report_headline_changes(Path::new(config_path))
+                .expect("Could not read headline changes.");
+        }
+        Some("check-sites") => {
+            let config_path = args.get(2).map_or("sites.toml", String::as_str);
+            let healthy = health::check_sites(Path::new(config_path))
+                .expect("Could not check site selectors.");
+            if !healthy {
+                std::process::exit(1);
+            }
+        }
+        _ => run_topic_model(),
+    }
+}
+
+fn run_topic_model() {
     let root_dir = Path::new("scraped");
-    let raw_data =
-        prepare_dataset::get_raw_dataset(root_dir, 0.2).expect("Dataset could not be loaded.");
-    let tokenizer = load_tokenizer().expect("Could not load tokenizer.");
-    println!("Tokenizer loaded...");
+    let filter = prepare_dataset::DatasetFilter::default();
+    let raw_data = prepare_dataset::get_raw_dataset(root_dir, 0.2, &filter)
+        .expect("Dataset could not be loaded.");
+    let tokenizers = load_tokenizers(Path::new(".")).expect("Could not load tokenizers.");
+    println!("Tokenizers loaded...");
+    println!(
+        "Training on {} articles in {} languages from {} sections by {} authors",
+        raw_data.train.len(),
+        prepare_dataset::by_language(&raw_data.train).len(),
+        prepare_dataset::by_section(&raw_data.train).len(),
+        prepare_dataset::by_author(&raw_data.train).len()
+    );
 
-    let indexer = preprocess::create_word_indexer(&tokenizer, &raw_data.train)
+    let indexer = preprocess::create_word_indexer(&tokenizers, &raw_data.train)
         .expect("Cannot construct indexer.");
     let deindexer = preprocess::create_word_deindexer(&indexer);
 
-    let token_counter = tf_idf::get_token_article_frequencies(&tokenizer, &raw_data.train)
+    let token_counter = tf_idf::get_token_article_frequencies(&tokenizers, &raw_data.train)
         .expect("Could not count headline token frequencies.");
 
-    dbg!(tf_idf::get_tfidf_distribution(&token_counter, &tokenizer, &raw_data.train));
+    dbg!(tf_idf::get_tfidf_distribution(&token_counter, &tokenizers, &raw_data.train));
 }
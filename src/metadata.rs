@@ -0,0 +1,399 @@
+use chrono::{DateTime, FixedOffset};
+use scraper::{Html, Selector};
+use serde_json::Value;
+
+use crate::dates::{self, parse_pub_date};
+
+// schema.org types that describe the article itself, as opposed to the
+// publisher, breadcrumbs or videos embedded alongside it
+const ARTICLE_TYPES: [&str; 8] = [
+    "NewsArticle",
+    "Article",
+    "ReportageNewsArticle",
+    "AnalysisNewsArticle",
+    "OpinionNewsArticle",
+    "BackgroundNewsArticle",
+    "LiveBlogPosting",
+    "BlogPosting",
+];
+
+// Article metadata published for search engines and social cards. JSON-LD is
+// read first, and OpenGraph/`article:` meta tags fill in whatever it lacks.
+#[derive(Debug, Clone, Default)]
+pub struct PageMetadata {
+    pub headline: Option<String>,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    pub section: Option<String>,
+    pub tags: Vec<String>,
+    pub published: Option<DateTime<FixedOffset>>,
+    pub modified: Option<DateTime<FixedOffset>>,
+    pub image: Option<String>,
+    pub url: Option<String>,
+    pub language: Option<String>,
+    pub accessible_for_free: Option<bool>,
+}
+
+impl PageMetadata {
+    pub fn from_doc(doc: &Html) -> Self {
+        let mut metadata = json_ld(doc).unwrap_or_default();
+        metadata.fill_from(meta_tags(doc));
+        metadata
+    }
+
+    fn fill_from(&mut self, other: PageMetadata) {
+        self.headline = self.headline.take().or(other.headline);
+        self.description = self.description.take().or(other.description);
+        self.section = self.section.take().or(other.section);
+        self.published = self.published.or(other.published);
+        self.modified = self.modified.or(other.modified);
+        self.image = self.image.take().or(other.image);
+        self.url = self.url.take().or(other.url);
+        self.language = self.language.take().or(other.language);
+        self.accessible_for_free = self.accessible_for_free.or(other.accessible_for_free);
+        if self.authors.is_empty() {
+            self.authors = other.authors;
+        }
+        if self.tags.is_empty() {
+            self.tags = other.tags;
+        }
+    }
+}
+
+fn clean_text(text: &str) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+fn parse_date(raw: &str) -> Option<DateTime<FixedOffset>> {
+    parse_pub_date(raw, dates::now()).ok()
+}
+
+fn push_unique(values: &mut Vec<String>, value: String) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+fn is_article(value: &Value) -> bool {
+    match value.get("@type") {
+        Some(Value::String(kind)) => ARTICLE_TYPES.contains(&kind.as_str()),
+        Some(Value::Array(kinds)) => kinds
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|kind| ARTICLE_TYPES.contains(&kind)),
+        _ => false,
+    }
+}
+
+// JSON-LD blocks may hold a single object, an array of them, or an `@graph`.
+fn find_article(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Array(values) => values.iter().find_map(find_article),
+        Value::Object(object) => {
+            if is_article(value) {
+                return Some(value);
+            }
+            object.get("@graph").and_then(find_article)
+        }
+        _ => None,
+    }
+}
+
+// Strings, or lists of strings and `{"name": ...}` objects.
+fn names(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(name) => clean_text(name).into_iter().collect(),
+        Value::Array(values) => values.iter().flat_map(names).collect(),
+        Value::Object(object) => object
+            .get("name")
+            .and_then(Value::as_str)
+            .and_then(clean_text)
+            .into_iter()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Images and URLs come as a string, an `{"url": ...}` object or a list of either.
+fn first_url(value: &Value) -> Option<String> {
+    match value {
+        Value::String(url) => clean_text(url),
+        Value::Array(values) => values.iter().find_map(first_url),
+        Value::Object(object) => object.get("url").or(object.get("@id")).and_then(first_url),
+        _ => None,
+    }
+}
+
+fn keywords(value: &Value) -> Vec<String> {
+    match value {
+        // a single string is comma-separated
+        Value::String(keywords) => keywords.split(',').filter_map(clean_text).collect(),
+        Value::Array(values) => values.iter().flat_map(keywords).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// `isAccessibleForFree` is a boolean or "True"/"False", set on the article or
+// on the `hasPart` sections of Google's paywalled-content markup.
+fn accessible_for_free(value: &Value) -> Option<bool> {
+    let part_locked = match value.get("hasPart") {
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .any(|part| accessible_for_free(part) == Some(false)),
+        Some(part) => accessible_for_free(part) == Some(false),
+        None => false,
+    };
+    if part_locked {
+        return Some(false);
+    }
+    match value.get("isAccessibleForFree")? {
+        Value::Bool(free) => Some(*free),
+        Value::String(free) => free.trim().to_lowercase().parse().ok(),
+        _ => None,
+    }
+}
+
+fn json_ld(doc: &Html) -> Option<PageMetadata> {
+    let script_selector = Selector::parse("script[type=\"application/ld+json\"]")
+        .expect("Unable to construct selector.");
+    let article = doc
+        .select(&script_selector)
+        .filter_map(|script| serde_json::from_str::<Value>(&script.text().collect::<String>()).ok())
+        .find_map(|value| find_article(&value).cloned())?;
+
+    let text = |key: &str| {
+        article
+            .get(key)
+            .and_then(Value::as_str)
+            .and_then(clean_text)
+    };
+    let mut authors = Vec::new();
+    for author in article.get("author").map(names).unwrap_or_default() {
+        push_unique(&mut authors, author);
+    }
+    let mut tags = Vec::new();
+    for tag in article.get("keywords").map(keywords).unwrap_or_default() {
+        push_unique(&mut tags, tag);
+    }
+
+    Some(PageMetadata {
+        headline: text("headline").or_else(|| text("name")),
+        description: text("description"),
+        authors,
+        section: article
+            .get("articleSection")
+            .map(names)
+            .and_then(|sections| sections.into_iter().next()),
+        tags,
+        published: text("datePublished").and_then(|date| parse_date(&date)),
+        modified: text("dateModified").and_then(|date| parse_date(&date)),
+        image: article.get("image").and_then(first_url),
+        url: article
+            .get("url")
+            .or(article.get("mainEntityOfPage"))
+            .and_then(first_url),
+        language: text("inLanguage").map(|lang| lang.to_lowercase()),
+        accessible_for_free: accessible_for_free(&article),
+    })
+}
+
+fn meta_values(doc: &Html, selector: &str) -> Vec<String> {
+    let meta_selector = Selector::parse(selector).expect("Unable to construct selector.");
+    let mut values = Vec::new();
+    for value in doc
+        .select(&meta_selector)
+        .filter_map(|elem| elem.value().attr("content"))
+        .filter_map(clean_text)
+    {
+        push_unique(&mut values, value);
+    }
+    values
+}
+
+fn meta_tags(doc: &Html) -> PageMetadata {
+    let first = |selectors: &[&str]| {
+        selectors
+            .iter()
+            .find_map(|selector| meta_values(doc, selector).into_iter().next())
+    };
+
+    // article:author is sometimes a profile URL rather than a name
+    let mut authors: Vec<String> = meta_values(doc, "meta[property=\"article:author\"]")
+        .into_iter()
+        .filter(|author| !author.starts_with("http"))
+        .collect();
+    if authors.is_empty() {
+        authors = meta_values(doc, "meta[name=\"author\"]");
+    }
+
+    let mut tags = meta_values(doc, "meta[property=\"article:tag\"]");
+    if tags.is_empty() {
+        for selector in ["meta[name=\"news_keywords\"]", "meta[name=\"keywords\"]"] {
+            for tag in meta_values(doc, selector)
+                .iter()
+                .flat_map(|tags| tags.split(','))
+            {
+                if let Some(tag) = clean_text(tag) {
+                    push_unique(&mut tags, tag);
+                }
+            }
+        }
+    }
+
+    PageMetadata {
+        headline: first(&[
+            "meta[property=\"og:title\"]",
+            "meta[name=\"twitter:title\"]",
+        ]),
+        description: first(&[
+            "meta[property=\"og:description\"]",
+            "meta[name=\"description\"]",
+        ]),
+        authors,
+        section: first(&["meta[property=\"article:section\"]"]),
+        tags,
+        published: first(&["meta[property=\"article:published_time\"]"])
+            .and_then(|date| parse_date(&date)),
+        modified: first(&["meta[property=\"article:modified_time\"]"])
+            .and_then(|date| parse_date(&date)),
+        image: first(&["meta[property=\"og:image\"]"]),
+        url: first(&["meta[property=\"og:url\"]"]),
+        language: None,
+        // Postmedia and others tag subscriber-only stories as "locked"
+        accessible_for_free: first(&["meta[property=\"article:content_tier\"]"]).and_then(|tier| {
+            match tier.to_lowercase().as_str() {
+                "free" => Some(true),
+                "locked" => Some(false),
+                _ => None,
+            }
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_head(head: &str) -> PageMetadata {
+        PageMetadata::from_doc(&Html::parse_document(&format!(
+            "<html><head>{head}</head><body></body></html>"
+        )))
+    }
+
+    fn json_ld_script(json: &str) -> String {
+        format!("<script type=\"application/ld+json\">{json}</script>")
+    }
+
+    #[test]
+    fn reads_the_article_from_a_graph() {
+        let metadata = from_head(&json_ld_script(
+            r#"{"@context": "https://schema.org", "@graph": [
+                {"@type": "WebSite", "name": "Example News", "url": "https://example.com/"},
+                {"@type": "BreadcrumbList", "itemListElement": []},
+                {"@type": ["NewsArticle", "Article"],
+                 "headline": "  Council passes\n budget ",
+                 "author": [{"@type": "Person", "name": "Jane Doe"}, "John Roe", {"name": "Jane Doe"}],
+                 "articleSection": ["Canada", "Politics"],
+                 "keywords": "budget, council, , taxes",
+                 "datePublished": "2023-03-02T18:40:00-05:00",
+                 "image": [{"url": "https://example.com/budget.jpg"}],
+                 "mainEntityOfPage": {"@id": "https://example.com/news/budget"},
+                 "inLanguage": "en-CA"}
+            ]}"#,
+        ));
+        assert_eq!(metadata.headline.as_deref(), Some("Council passes budget"));
+        assert_eq!(metadata.authors, ["Jane Doe", "John Roe"]);
+        assert_eq!(metadata.section.as_deref(), Some("Canada"));
+        assert_eq!(metadata.tags, ["budget", "council", "taxes"]);
+        assert_eq!(
+            metadata.published.map(|date| date.to_rfc3339()).as_deref(),
+            Some("2023-03-02T18:40:00-05:00")
+        );
+        assert_eq!(
+            metadata.image.as_deref(),
+            Some("https://example.com/budget.jpg")
+        );
+        assert_eq!(
+            metadata.url.as_deref(),
+            Some("https://example.com/news/budget")
+        );
+        assert_eq!(metadata.language.as_deref(), Some("en-ca"));
+    }
+
+    #[test]
+    fn reads_the_article_from_an_array() {
+        let metadata = from_head(&format!(
+            "{}{}",
+            json_ld_script("{not json"),
+            json_ld_script(
+                r#"[{"@type": "Organization", "name": "Example News"},
+                    {"@type": "ReportageNewsArticle", "name": "Transit strike",
+                     "keywords": ["transit", "labour", "transit"]}]"#
+            )
+        ));
+        // a broken block is skipped, and `name` stands in for `headline`
+        assert_eq!(metadata.headline.as_deref(), Some("Transit strike"));
+        assert_eq!(metadata.tags, ["transit", "labour"]);
+    }
+
+    #[test]
+    fn fills_gaps_from_opengraph_tags() {
+        let metadata = from_head(&format!(
+            "{}{}",
+            json_ld_script(r#"{"@type": "NewsArticle", "headline": "From JSON-LD"}"#),
+            r#"<meta property="og:title" content="From OpenGraph">
+               <meta property="og:description" content="The story in a sentence.">
+               <meta property="og:url" content="https://example.com/news/story">
+               <meta property="article:author" content="https://example.com/staff/jdoe">
+               <meta name="author" content="Jane Doe">
+               <meta property="article:section" content="World">
+               <meta name="news_keywords" content="summit, climate">
+               <meta property="article:published_time" content="2023-03-03T16:30:00-05:00">"#
+        ));
+        assert_eq!(metadata.headline.as_deref(), Some("From JSON-LD"));
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("The story in a sentence.")
+        );
+        assert_eq!(
+            metadata.url.as_deref(),
+            Some("https://example.com/news/story")
+        );
+        // profile URLs in article:author give way to the author meta tag
+        assert_eq!(metadata.authors, ["Jane Doe"]);
+        assert_eq!(metadata.section.as_deref(), Some("World"));
+        assert_eq!(metadata.tags, ["summit", "climate"]);
+        assert!(metadata.published.is_some());
+
+        // pages without JSON-LD rely on the tags alone
+        let metadata = from_head(r#"<meta property="og:title" content="From OpenGraph">"#);
+        assert_eq!(metadata.headline.as_deref(), Some("From OpenGraph"));
+    }
+
+    #[test]
+    fn reads_whether_the_article_is_free() {
+        let free = |json: &str| from_head(&json_ld_script(json)).accessible_for_free;
+        assert_eq!(
+            free(r#"{"@type": "NewsArticle", "isAccessibleForFree": true}"#),
+            Some(true)
+        );
+        assert_eq!(
+            free(r#"{"@type": "NewsArticle", "isAccessibleForFree": "False"}"#),
+            Some(false)
+        );
+        // a locked part locks the whole article
+        assert_eq!(
+            free(
+                r#"{"@type": "NewsArticle", "isAccessibleForFree": "True",
+                    "hasPart": [{"@type": "WebPageElement", "isAccessibleForFree": false,
+                                 "cssSelector": ".paywall"}]}"#
+            ),
+            Some(false)
+        );
+        assert_eq!(free(r#"{"@type": "NewsArticle"}"#), None);
+
+        let locked = from_head(r#"<meta property="article:content_tier" content="Locked">"#);
+        assert_eq!(locked.accessible_for_free, Some(false));
+    }
+}
@@ -0,0 +1,156 @@
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+// Why an article looks like it was cut short for non-subscribers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaywallSignal {
+    // the page's metadata marks it as not free (`isAccessibleForFree`)
+    NotFree,
+    // one of the site's `paywall_selectors` matched
+    Overlay,
+    // a paragraph asks the reader to subscribe or sign in
+    SubscribePrompt,
+    // the text stops mid-sentence or on an ellipsis
+    Truncated,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PaywallCheck {
+    pub signals: Vec<PaywallSignal>,
+    // whether the saved text is likely only part of the story
+    pub partial: bool,
+}
+
+const SUBSCRIBE_PROMPTS: [&str; 8] = [
+    "subscribe to continue reading",
+    "subscribe to read",
+    "subscribers only",
+    "already a subscriber",
+    "to continue reading",
+    "to read the full story",
+    "sign in to continue",
+    "unlock this article",
+];
+
+// Paywalled pages often carry the full text for search engines, and captions
+// or inline promos can trip the text checks, so a signal only counts as a
+// cut-off story when the body is this short. The exception is a subscribe
+// prompt in the last paragraph, which is where a paywall cuts the text.
+const STUB_WORDS: usize = 250;
+// Credits and captions routinely end without a full stop; only a paragraph
+// this long ending mid-sentence is taken as truncated.
+const MIN_TRUNCATED_WORDS: usize = 20;
+const TERMINAL_CHARS: [char; 10] = ['.', '!', '?', '"', '\'', '”', '’', ')', ':', ';'];
+
+fn has_subscribe_prompt(paragraph: &str) -> bool {
+    let paragraph = paragraph.to_lowercase();
+    SUBSCRIBE_PROMPTS
+        .iter()
+        .any(|prompt| paragraph.contains(prompt))
+}
+
+fn ends_abruptly(paragraph: &str) -> bool {
+    let paragraph = paragraph.trim_end();
+    if paragraph.ends_with('…') || paragraph.ends_with("...") {
+        return true;
+    }
+    paragraph.split_whitespace().count() >= MIN_TRUNCATED_WORDS
+        && !paragraph.ends_with(TERMINAL_CHARS)
+}
+
+pub fn check_paywall(
+    doc: &Html,
+    accessible_for_free: Option<bool>,
+    paywall_selectors: &[String],
+    paragraphs: &[String],
+) -> PaywallCheck {
+    let mut signals = Vec::new();
+    if accessible_for_free == Some(false) {
+        signals.push(PaywallSignal::NotFree);
+    }
+    if paywall_selectors.iter().any(|selector| {
+        let paywall_selector = Selector::parse(selector).expect("Unable to construct selector.");
+        doc.select(&paywall_selector).next().is_some()
+    }) {
+        signals.push(PaywallSignal::Overlay);
+    }
+    if paragraphs
+        .iter()
+        .any(|paragraph| has_subscribe_prompt(paragraph))
+    {
+        signals.push(PaywallSignal::SubscribePrompt);
+    }
+    if paragraphs.last().is_some_and(|last| ends_abruptly(last)) {
+        signals.push(PaywallSignal::Truncated);
+    }
+
+    let words: usize = paragraphs
+        .iter()
+        .map(|paragraph| paragraph.split_whitespace().count())
+        .sum();
+    let prompt_at_end = paragraphs
+        .last()
+        .is_some_and(|last| has_subscribe_prompt(last));
+    let partial = (words < STUB_WORDS && !signals.is_empty()) || prompt_at_end;
+    PaywallCheck { signals, partial }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(paragraphs: &[String]) -> PaywallCheck {
+        check_paywall(&Html::parse_document(""), None, &[], paragraphs)
+    }
+
+    fn long_story(words: usize) -> Vec<String> {
+        let sentence = "The council voted on the budget late on Tuesday night. ";
+        let paragraph = sentence.repeat(10);
+        let n = words.div_ceil(paragraph.split_whitespace().count());
+        vec![paragraph.trim().to_string(); n]
+    }
+
+    #[test]
+    fn prompt_in_the_last_paragraph_marks_the_story_partial() {
+        let mut paragraphs = long_story(400);
+        paragraphs.push("Already a subscriber? Sign in to continue reading.".into());
+        let check = check(&paragraphs);
+        assert_eq!(check.signals, [PaywallSignal::SubscribePrompt]);
+        assert!(check.partial);
+    }
+
+    #[test]
+    fn prompt_inside_a_long_story_is_only_a_signal() {
+        let mut paragraphs = long_story(400);
+        paragraphs.insert(2, "Subscribe to read our newsletter on city hall.".into());
+        let check = check(&paragraphs);
+        assert_eq!(check.signals, [PaywallSignal::SubscribePrompt]);
+        assert!(!check.partial);
+    }
+
+    #[test]
+    fn truncation_only_counts_for_short_bodies() {
+        let cut = "The council voted on the budget late on Tuesday night after a long debate \
+                   that ran past midnight and left several members";
+        let mut paragraphs = long_story(400);
+        paragraphs.push(cut.into());
+        let check_long = check(&paragraphs);
+        assert_eq!(check_long.signals, [PaywallSignal::Truncated]);
+        assert!(!check_long.partial);
+
+        let check_short = check(&[cut.to_string()]);
+        assert_eq!(check_short.signals, [PaywallSignal::Truncated]);
+        assert!(check_short.partial);
+    }
+
+    #[test]
+    fn metadata_only_counts_for_short_bodies() {
+        let doc = Html::parse_document("");
+        let long = check_paywall(&doc, Some(false), &[], &long_story(400));
+        assert_eq!(long.signals, [PaywallSignal::NotFree]);
+        assert!(!long.partial);
+        let short = check_paywall(&doc, Some(false), &[], &long_story(50));
+        assert!(short.partial);
+    }
+}
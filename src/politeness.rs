@@ -0,0 +1,341 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::{Client, Url};
+use tokio::{
+    sync::Mutex as AsyncMutex,
+    time::{sleep, Instant},
+};
+
+use crate::errors::ScrapeError;
+use crate::fetcher::FetchMode;
+use crate::site_config::CrawlerConfig;
+
+const DISALLOW_ALL: &str = "User-agent: *\nDisallow: /\n";
+
+#[derive(Debug, Clone)]
+struct RobotsRule {
+    allow: bool,
+    pattern: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    rules: Vec<RobotsRule>,
+    crawl_delay: Option<Duration>,
+    sitemaps: Vec<String>,
+}
+
+// Matches a robots.txt path pattern, where `*` matches any run of characters
+// and a trailing `$` anchors the pattern at the end of the path.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !path.starts_with(first) {
+        return false;
+    }
+    let mut rest = &path[first.len()..];
+    let parts: Vec<&str> = parts.collect();
+
+    for (i, part) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        if is_last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
+fn product_token(user_agent: &str) -> String {
+    user_agent
+        .split('/')
+        .next()
+        .unwrap_or(user_agent)
+        .trim()
+        .to_lowercase()
+}
+
+impl RobotsRules {
+    // Picks the group addressed to our user agent, falling back to the `*` group.
+    // Groups match on the whole product token ("news_scraper" in
+    // "news_scraper/0.1"), ignoring case, as RFC 9309 specifies.
+    pub fn parse(text: &str, user_agent: &str) -> Self {
+        let agent_token = product_token(user_agent);
+
+        let mut specific: Option<RobotsRules> = None;
+        let mut wildcard: Option<RobotsRules> = None;
+
+        let mut group_agents: Vec<String> = Vec::new();
+        let mut group = RobotsRules::default();
+        let mut in_rules = false;
+        let mut sitemaps = Vec::new();
+
+        let mut finish_group = |agents: &Vec<String>, group: RobotsRules| {
+            if agents
+                .iter()
+                .any(|agent| agent != "*" && product_token(agent) == agent_token)
+            {
+                specific
+                    .get_or_insert_with(RobotsRules::default)
+                    .merge(group);
+            } else if agents.iter().any(|agent| agent == "*") {
+                wildcard
+                    .get_or_insert_with(RobotsRules::default)
+                    .merge(group);
+            }
+        };
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    // a user-agent line after rules starts a new group
+                    if in_rules {
+                        finish_group(&group_agents, std::mem::take(&mut group));
+                        group_agents.clear();
+                        in_rules = false;
+                    }
+                    group_agents.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // an empty Disallow means everything is allowed
+                    if !value.is_empty() {
+                        group.rules.push(RobotsRule {
+                            allow: key == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    if let Ok(seconds) = value.parse::<f64>() {
+                        group.crawl_delay = Some(Duration::from_secs_f64(seconds));
+                    }
+                }
+                // Sitemap lines apply to every user agent
+                "sitemap" => sitemaps.push(value.to_string()),
+                _ => (),
+            }
+        }
+        finish_group(&group_agents, group);
+
+        let mut rules = specific.or(wildcard).unwrap_or_default();
+        rules.sitemaps = sitemaps;
+        rules
+    }
+
+    fn merge(&mut self, other: RobotsRules) {
+        self.rules.extend(other.rules);
+        self.crawl_delay = self.crawl_delay.or(other.crawl_delay);
+    }
+
+    // The longest matching pattern wins, with Allow winning ties.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let mut best: Option<&RobotsRule> = None;
+        for rule in &self.rules {
+            if !pattern_matches(&rule.pattern, path) {
+                continue;
+            }
+            best = match best {
+                Some(current)
+                    if current.pattern.len() > rule.pattern.len()
+                        || (current.pattern.len() == rule.pattern.len() && current.allow) =>
+                {
+                    Some(current)
+                }
+                _ => Some(rule),
+            };
+        }
+        best.is_none_or(|rule| rule.allow)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+
+    pub fn sitemaps(&self) -> &[String] {
+        &self.sitemaps
+    }
+}
+
+pub struct Politeness {
+    client: Client,
+    mode: FetchMode,
+    user_agent: String,
+    min_delay: Duration,
+    robots: AsyncMutex<HashMap<String, RobotsRules>>,
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+pub fn host_key(url: &Url) -> Result<String> {
+    let host = url.host_str().ok_or(anyhow!("URL {url} has no host."))?;
+    Ok(match url.port() {
+        Some(port) => format!("{}://{host}:{port}", url.scheme()),
+        None => format!("{}://{host}", url.scheme()),
+    })
+}
+
+impl Politeness {
+    pub fn new(config: &CrawlerConfig, client: Client, mode: FetchMode) -> Self {
+        Politeness {
+            client,
+            mode,
+            user_agent: config.user_agent.clone(),
+            min_delay: Duration::from_millis(config.min_delay_ms),
+            robots: AsyncMutex::new(HashMap::new()),
+            next_slot: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Missing files and failed fetches come back as the robots.txt they stand
+    // for, so the outcome can be recorded as a fixture like any other page.
+    async fn fetch_robots(&self, robots_url: &str) -> String {
+        match self.client.get(robots_url).send().await {
+            Ok(res) if res.status().is_success() => {
+                res.text().await.unwrap_or_else(|_| DISALLOW_ALL.into())
+            }
+            // no robots.txt means no restrictions
+            Ok(res) if res.status().is_client_error() => String::new(),
+            // server errors and unreachable hosts are treated as a full disallow
+            _ => DISALLOW_ALL.into(),
+        }
+    }
+
+    async fn load_robots(&self, host: &str) -> Result<String> {
+        let robots_url = format!("{host}/robots.txt");
+        match &self.mode {
+            FetchMode::Live => Ok(self.fetch_robots(&robots_url).await),
+            FetchMode::Record(fixtures) => {
+                let text = self.fetch_robots(&robots_url).await;
+                fixtures.save(&robots_url, &text)?;
+                Ok(text)
+            }
+            FetchMode::Replay(fixtures) => fixtures
+                .load(&robots_url)
+                .with_context(|| format!("Cannot replay robots.txt for {host}; record it first")),
+        }
+    }
+
+    // The lock is held while robots.txt is fetched so concurrent requests to a
+    // new host only fetch it once.
+    pub async fn rules_for(&self, url: &Url) -> Result<RobotsRules> {
+        let host = host_key(url)?;
+        let mut robots = self.robots.lock().await;
+        if !robots.contains_key(&host) {
+            let rules = RobotsRules::parse(&self.load_robots(&host).await?, &self.user_agent);
+            robots.insert(host.clone(), rules);
+        }
+        Ok(robots[&host].clone())
+    }
+
+    // Fails if robots.txt disallows the URL.
+    pub async fn check_allowed(&self, url: &Url) -> Result<RobotsRules> {
+        let path = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        let rules = self.rules_for(url).await?;
+        if !rules.is_allowed(&path) {
+            return Err(ScrapeError::RobotsDisallowed.into());
+        }
+        Ok(rules)
+    }
+
+    // Checks robots.txt, then waits for the next free slot on the host. Slots
+    // are spaced by the larger of the configured minimum delay and the host's
+    // Crawl-delay, and are reserved up front so concurrent requests to one host
+    // queue behind each other.
+    pub async fn before_request(&self, href: &str) -> Result<()> {
+        let url = Url::parse(href)?;
+        let rules = self.check_allowed(&url).await?;
+
+        let delay = self.min_delay.max(rules.crawl_delay().unwrap_or_default());
+        let host = host_key(&url)?;
+        let wait = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let now = Instant::now();
+            let slot = next_slot.get(&host).map_or(now, |next| (*next).max(now));
+            next_slot.insert(host, slot + delay);
+            slot - now
+        };
+        sleep(wait).await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "
+User-agent: *
+Disallow: /search
+Allow: /search/about$
+Crawl-delay: 2
+
+User-agent: news
+Disallow: /
+
+User-agent: News_Scraper
+Disallow: /private/
+Disallow: /*.pdf$
+Allow: /private/public-*
+
+Sitemap: https://example.com/sitemap.xml
+";
+
+    #[test]
+    fn picks_the_group_for_our_product_token() {
+        let rules = RobotsRules::parse(ROBOTS, "news_scraper/0.1");
+        assert!(rules.is_allowed("/news/story"));
+        assert!(!rules.is_allowed("/private/notes"));
+        assert!(rules.is_allowed("/private/public-notes"));
+        // the `*` group doesn't apply once a specific group matched
+        assert!(rules.is_allowed("/search"));
+        assert_eq!(rules.crawl_delay(), None);
+        assert_eq!(rules.sitemaps(), ["https://example.com/sitemap.xml"]);
+    }
+
+    #[test]
+    fn ignores_groups_for_other_agents_sharing_a_prefix() {
+        let robots = "User-agent: news\nDisallow: /\n\nUser-agent: s\nDisallow: /\n";
+        let rules = RobotsRules::parse(robots, "news_scraper/0.1");
+        assert!(rules.is_allowed("/news/story"));
+    }
+
+    #[test]
+    fn falls_back_to_the_wildcard_group() {
+        let rules = RobotsRules::parse(ROBOTS, "other_bot/1.0");
+        assert!(!rules.is_allowed("/search?q=news"));
+        assert!(rules.is_allowed("/search/about"));
+        assert!(!rules.is_allowed("/search/about/more"));
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn matches_wildcards_and_anchors() {
+        let rules = RobotsRules::parse(ROBOTS, "news_scraper");
+        assert!(!rules.is_allowed("/files/report.pdf"));
+        assert!(rules.is_allowed("/files/report.pdf?download=1"));
+        assert!(RobotsRules::parse("", "news_scraper").is_allowed("/anything"));
+        assert!(!RobotsRules::parse(DISALLOW_ALL, "news_scraper").is_allowed("/"));
+    }
+}
@@ -1,14 +1,79 @@
 use anyhow::Result;
+use chrono::NaiveDate;
 use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use rand::SeedableRng;
-use std::{fs, io, path::*};
+use std::{collections::BTreeMap, fs, io, path::*};
 
+use crate::article::{is_article_record, load_article};
+use crate::dates::parse_dir_date;
+use crate::language::article_language;
+use crate::wire::WireService;
+
+// Authors, section, tags, wire service, language and paywall status are only
+// known for JSON records; legacy .txt articles leave them empty.
 #[derive(Debug, Clone)]
 pub struct ArticleEntry {
-    pub date: String,
+    pub date: NaiveDate,
     pub path: PathBuf,
     pub site: String,
+    pub authors: Vec<String>,
+    pub section: Option<String>,
+    pub tags: Vec<String>,
+    pub wire: Option<WireService>,
+    pub language: Option<String>,
+    pub partial: bool,
+}
+
+impl ArticleEntry {
+    fn new(date: NaiveDate, path: PathBuf, site: &str) -> Self {
+        let mut entry = ArticleEntry {
+            date,
+            path,
+            site: String::from(site),
+            authors: Vec::new(),
+            section: None,
+            tags: Vec::new(),
+            wire: None,
+            language: None,
+            partial: false,
+        };
+        if is_article_record(&entry.path) {
+            match load_article(&entry.path) {
+                Ok(article) => {
+                    entry.language = article_language(&article);
+                    entry.authors = article.authors;
+                    entry.section = article.section.map(|section| section.to_lowercase());
+                    entry.tags = article.tags;
+                    entry.wire = article.wire.map(|wire| wire.service);
+                    entry.partial = article.partial;
+                }
+                Err(err) => println!("{err}"),
+            }
+        }
+        entry
+    }
+}
+
+// Which stored articles make it into a dataset.
+#[derive(Debug, Clone, Default)]
+pub struct DatasetFilter {
+    // keep paywall stubs and truncated articles, which are left out by default
+    pub include_partial: bool,
+    // ISO 639-1 codes to keep, e.g. ["en"]; empty keeps every language.
+    // Articles of unknown language are left out whenever this is set.
+    pub languages: Vec<String>,
+}
+
+impl DatasetFilter {
+    fn keeps(&self, entry: &ArticleEntry) -> bool {
+        (self.include_partial || !entry.partial)
+            && (self.languages.is_empty()
+                || entry
+                    .language
+                    .as_ref()
+                    .is_some_and(|language| self.languages.contains(language)))
+    }
 }
 
 #[derive(Debug)]
@@ -32,36 +97,38 @@ fn get_news_site_paths(root_dir: &Path, site: String) -> Result<Vec<ArticleEntry
     Ok(entries)
 }
 
-fn parse_date(date_buf: &PathBuf) -> String {
+fn parse_date(date_buf: &PathBuf) -> Result<NaiveDate> {
     let date_slice = date_buf
         .file_name()
         .expect("Cannot get file name.")
         .to_str()
         .expect("Cannot convert path to string.");
-    String::from(date_slice)
-        .replace("March", "03")
-        .replace("Mar", "03")
-        .replace("Feb", "02")
+    parse_dir_date(date_slice)
 }
 
 fn read_day_path(day_dir: &PathBuf, site: &String) -> Result<Vec<ArticleEntry>> {
+    let date = parse_date(day_dir)?;
     let article_paths = fs::read_dir(day_dir)?
-        .map(|res| {
-            res.map(|e| ArticleEntry {
-                date: parse_date(day_dir),
-                path: e.path(),
-                site: String::from(site),
-            })
-        })
+        .map(|res| res.map(|e| ArticleEntry::new(date, e.path(), site)))
         .collect::<Result<Vec<_>, io::Error>>()?;
 
     return Ok(article_paths);
 }
 
-pub fn get_raw_dataset(scraped_path: &Path, train_test_split: f32) -> Result<RawDataset> {
-    let sites = fs::read_dir(scraped_path)?
+pub fn get_raw_dataset(
+    scraped_path: &Path,
+    train_test_split: f32,
+    filter: &DatasetFilter,
+) -> Result<RawDataset> {
+    let mut sites = fs::read_dir(scraped_path)?
         .map(|res| res.map(|e| e.path()))
         .collect::<Result<Vec<_>, io::Error>>()?;
+    // skip the response cache and other hidden directories
+    sites.retain(|dir| {
+        dir.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| !name.starts_with('.'))
+    });
     let mut articles: Vec<ArticleEntry> = sites
         .into_iter()
         .flat_map(|dir| {
@@ -79,6 +146,15 @@ pub fn get_raw_dataset(scraped_path: &Path, train_test_split: f32) -> Result<Raw
         .flatten()
         .collect();
 
+    let n_articles = articles.len();
+    articles.retain(|article| filter.keeps(article));
+    if articles.len() < n_articles {
+        println!(
+            "Left out {} paywalled, truncated or other-language articles",
+            n_articles - articles.len()
+        );
+    }
+
     let mut rng = StdRng::seed_from_u64(12345);
     articles.shuffle(&mut rng);
 
@@ -88,3 +164,79 @@ pub fn get_raw_dataset(scraped_path: &Path, train_test_split: f32) -> Result<Raw
         test: articles[n_train..].to_vec(),
     })
 }
+
+// Groups articles by every key `keys` returns for them, so an article with two
+// authors lands in both authors' groups. Articles without a key are left out.
+pub fn group_entries<F>(entries: &[ArticleEntry], keys: F) -> BTreeMap<String, Vec<ArticleEntry>>
+where
+    F: Fn(&ArticleEntry) -> Vec<String>,
+{
+    let mut groups: BTreeMap<String, Vec<ArticleEntry>> = BTreeMap::new();
+    for entry in entries {
+        for key in keys(entry) {
+            groups.entry(key).or_default().push(entry.clone());
+        }
+    }
+    groups
+}
+
+pub fn by_author(entries: &[ArticleEntry]) -> BTreeMap<String, Vec<ArticleEntry>> {
+    group_entries(entries, |entry| entry.authors.clone())
+}
+
+pub fn by_section(entries: &[ArticleEntry]) -> BTreeMap<String, Vec<ArticleEntry>> {
+    group_entries(entries, |entry| entry.section.iter().cloned().collect())
+}
+
+pub fn by_language(entries: &[ArticleEntry]) -> BTreeMap<String, Vec<ArticleEntry>> {
+    group_entries(entries, |entry| entry.language.iter().cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(language: Option<&str>, partial: bool) -> ArticleEntry {
+        ArticleEntry {
+            date: NaiveDate::from_ymd_opt(2023, 3, 6).unwrap(),
+            path: PathBuf::from("scraped/cbc/2023-03-06/story.json"),
+            site: "cbc".to_string(),
+            authors: Vec::new(),
+            section: None,
+            tags: Vec::new(),
+            wire: None,
+            language: language.map(String::from),
+            partial,
+        }
+    }
+
+    #[test]
+    fn keeps_every_language_by_default() {
+        let filter = DatasetFilter::default();
+        assert!(filter.keeps(&entry(Some("en"), false)));
+        assert!(filter.keeps(&entry(Some("fr"), false)));
+        assert!(filter.keeps(&entry(None, false)));
+        assert!(!filter.keeps(&entry(Some("en"), true)));
+    }
+
+    #[test]
+    fn keeps_only_the_listed_languages() {
+        let filter = DatasetFilter {
+            languages: vec!["en".to_string(), "fr".to_string()],
+            ..DatasetFilter::default()
+        };
+        assert!(filter.keeps(&entry(Some("en"), false)));
+        assert!(filter.keeps(&entry(Some("fr"), false)));
+        assert!(!filter.keeps(&entry(Some("de"), false)));
+        // unknown languages can't be shown to match
+        assert!(!filter.keeps(&entry(None, false)));
+        // partial articles are still left out unless asked for
+        assert!(!filter.keeps(&entry(Some("en"), true)));
+        let filter = DatasetFilter {
+            include_partial: true,
+            ..filter
+        };
+        assert!(filter.keeps(&entry(Some("en"), true)));
+        assert!(!filter.keeps(&entry(Some("de"), true)));
+    }
+}
@@ -2,9 +2,11 @@ use anyhow;
 use nlprule::Tokenizer;
 use std::collections::hash_map::RandomState;
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
-use std::{fs, io};
+use std::path::{Path, PathBuf};
+use std::fs;
+use unicode_segmentation::UnicodeSegmentation;
 
+use crate::article::{is_article_record, load_article};
 use crate::prepare_dataset::ArticleEntry;
 
 
@@ -56,38 +58,125 @@ impl Deindexer {
     }
 }
 
-pub fn load_tokenizer() -> anyhow::Result<Tokenizer> {
-    println!("Loading tokenizer...");
-    Ok(Tokenizer::new("./en_tokenizer.bin")?)
+// nlprule only publishes tokenizer binaries for these languages
+pub const TOKENIZER_LANGUAGES: [&str; 3] = ["en", "de", "es"];
+// legacy .txt scrapes have no language and were all English
+const DEFAULT_LANGUAGE: &str = "en";
+
+pub trait TextTokenizer {
+    fn tokens(&self, text: &str) -> Vec<String>;
+}
+
+impl TextTokenizer for Tokenizer {
+    fn tokens(&self, text: &str) -> Vec<String> {
+        let tokens = self.pipe(text).flat_map(|s| s.tokens().to_vec());
+        let tokens = tokens.map(|t| t.word().as_str().to_string());
+        tokens.collect()
+    }
+}
+
+// Unicode word boundaries, for languages nlprule has no binary for.
+pub struct SegmentationTokenizer;
+
+impl TextTokenizer for SegmentationTokenizer {
+    fn tokens(&self, text: &str) -> Vec<String> {
+        text.unicode_words().map(String::from).collect()
+    }
+}
+
+// Tokenizers by ISO 639-1 language code, so every article is tokenized in
+// its own language.
+pub struct TokenizerRegistry {
+    tokenizers: HashMap<String, Box<dyn TextTokenizer>>,
+    fallback: SegmentationTokenizer,
+}
+
+impl TokenizerRegistry {
+    pub fn new() -> Self {
+        TokenizerRegistry {
+            tokenizers: HashMap::new(),
+            fallback: SegmentationTokenizer,
+        }
+    }
+
+    pub fn register(&mut self, language: &str, tokenizer: Box<dyn TextTokenizer>) {
+        self.tokenizers.insert(String::from(language), tokenizer);
+    }
+
+    // languages without a registered tokenizer are split on word boundaries
+    pub fn get(&self, language: Option<&str>) -> &dyn TextTokenizer {
+        match self.tokenizers.get(language.unwrap_or(DEFAULT_LANGUAGE)) {
+            Some(tokenizer) => tokenizer.as_ref(),
+            None => &self.fallback,
+        }
+    }
+}
+
+fn tokenizer_path(dir: &Path, language: &str) -> PathBuf {
+    dir.join(format!("{}_tokenizer.bin", language))
+}
+
+// Loads `<dir>/<language>_tokenizer.bin`, e.g. en_tokenizer.bin for "en".
+pub fn load_tokenizer(dir: &Path, language: &str) -> anyhow::Result<Tokenizer> {
+    if !TOKENIZER_LANGUAGES.contains(&language) {
+        return Err(anyhow::anyhow!("No nlprule tokenizer for language {:?}", language));
+    }
+    let path = tokenizer_path(dir, language);
+    if !path.exists() {
+        return Err(anyhow::anyhow!("Missing {}", path.display()));
+    }
+    println!("Loading {} tokenizer...", language);
+    Ok(Tokenizer::new(path)?)
 }
 
-pub fn read_article_text(path: &Path) -> io::Result<String> {
-    let body = fs::read_to_string(path)?;
+// Registers an nlprule tokenizer for English, which is required, and for every
+// other language whose binary is in `dir`. Languages without one are split on
+// word boundaries.
+pub fn load_tokenizers(dir: &Path) -> anyhow::Result<TokenizerRegistry> {
+    let mut registry = TokenizerRegistry::new();
+    for language in TOKENIZER_LANGUAGES {
+        if language != DEFAULT_LANGUAGE && !tokenizer_path(dir, language).exists() {
+            println!("No {}_tokenizer.bin, splitting {} text on word boundaries", language, language);
+            continue;
+        }
+        registry.register(language, Box::new(load_tokenizer(dir, language)?));
+    }
+    Ok(registry)
+}
+
+pub fn read_article_text(path: &Path) -> anyhow::Result<String> {
+    // legacy scrapes stored bare paragraphs in .txt files
+    let body = if is_article_record(path) {
+        load_article(path)?.paragraphs.join("\n\n")
+    } else {
+        fs::read_to_string(path)?
+    };
     if body.len() > 0 {
         Ok(body.to_lowercase())
     } else {
-        Err(io::Error::new(io::ErrorKind::Other, "Article body empty"))
+        Err(anyhow::anyhow!("Article body empty"))
     }
 }
 
-fn get_tokens(tokenizer: &Tokenizer, text: &String) -> Vec<String> {
-    let tokens = tokenizer.pipe(text).flat_map(|s| s.tokens().to_vec());
-    let tokens = tokens.map(|t| t.word().as_str().to_string());
-    tokens.collect()
-}
-
 pub fn tokenize_article_text(
-    tokenizer: &Tokenizer,
-    article_path: &Path,
+    tokenizers: &TokenizerRegistry,
+    entry: &ArticleEntry,
 ) -> anyhow::Result<Vec<String>> {
-    let article_text = read_article_text(article_path)?;
-    Ok(get_tokens(tokenizer, &article_text))
+    let article_text = read_article_text(&entry.path)?;
+    Ok(tokenizers.get(entry.language.as_deref()).tokens(&article_text))
 }
 
 pub fn tokenize_headline_text(
-    tokenizer: &Tokenizer,
-    article_path: &Path,
+    tokenizers: &TokenizerRegistry,
+    entry: &ArticleEntry,
 ) -> anyhow::Result<Vec<String>> {
+    let tokenizer = tokenizers.get(entry.language.as_deref());
+    let article_path = &entry.path;
+    if is_article_record(article_path) {
+        let headline = load_article(article_path)?.title;
+        return Ok(tokenizer.tokens(&headline));
+    }
+
     let headline = article_path
         .file_stem()
         .ok_or(anyhow::anyhow!("Could not find stem."))?;
@@ -95,17 +184,17 @@ pub fn tokenize_headline_text(
         .to_str()
         .ok_or(anyhow::anyhow!("Could not convert to str"))?;
     let headline = String::from(headline).replace("_", " ");
-    Ok(get_tokens(tokenizer, &headline))
+    Ok(tokenizer.tokens(&headline))
 }
 
 pub fn create_word_indexer(
-    tokenizer: &Tokenizer,
+    tokenizers: &TokenizerRegistry,
     ds: &Vec<ArticleEntry>,
 ) -> anyhow::Result<Indexer> {
     let article_tokens = ds
         .into_iter()
         .map(
-            |entry| match tokenize_headline_text(tokenizer, &entry.path) {
+            |entry| match tokenize_headline_text(tokenizers, entry) {
                 Ok(tokens) => tokens,
                 Err(_) => vec![],
             },
@@ -129,3 +218,53 @@ pub fn create_word_deindexer(indexer: &Indexer) -> Deindexer {
     let deidxer = HashMap::from_iter(deidx_iter);
     Deindexer::new(deidxer)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tokenizes everything to its own name, to show which one was picked.
+    struct Named(&'static str);
+
+    impl TextTokenizer for Named {
+        fn tokens(&self, _text: &str) -> Vec<String> {
+            vec![self.0.to_string()]
+        }
+    }
+
+    fn registry() -> TokenizerRegistry {
+        let mut registry = TokenizerRegistry::new();
+        registry.register("en", Box::new(Named("en")));
+        registry.register("de", Box::new(Named("de")));
+        registry
+    }
+
+    #[test]
+    fn looks_up_each_language() {
+        let registry = registry();
+        assert_eq!(registry.get(Some("de")).tokens("Guten Tag"), ["de"]);
+        assert_eq!(registry.get(Some("en")).tokens("Good day"), ["en"]);
+    }
+
+    #[test]
+    fn articles_without_a_language_are_english() {
+        assert_eq!(registry().get(None).tokens("Good day"), ["en"]);
+    }
+
+    #[test]
+    fn other_languages_are_split_on_word_boundaries() {
+        assert_eq!(
+            registry().get(Some("fr")).tokens("Bonjour, l'équipe!"),
+            ["Bonjour", "l'équipe"]
+        );
+    }
+
+    #[test]
+    fn the_english_binary_is_required() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = load_tokenizers(dir.path()).err().unwrap();
+        assert!(err.to_string().contains("en_tokenizer.bin"), "{}", err);
+        let err = load_tokenizer(dir.path(), "fr").err().unwrap();
+        assert!(err.to_string().contains("No nlprule tokenizer"), "{}", err);
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::text::{Paragraph, TextNormalizer};
+
+// Tags whose text is never article content.
+const SKIPPED_TAGS: [&str; 10] = [
+    "script",
+    "style",
+    "noscript",
+    "nav",
+    "header",
+    "footer",
+    "aside",
+    "form",
+    "figcaption",
+    "button",
+];
+
+// Class and id fragments that mark likely (or unlikely) article containers.
+// Fragments ending in '-' only match at the start of a class name.
+const POSITIVE_HINTS: [&str; 7] = [
+    "article", "body", "content", "entry", "main", "story", "text",
+];
+const NEGATIVE_HINTS: [&str; 14] = [
+    "ad-",
+    "advert",
+    "comment",
+    "footer",
+    "masthead",
+    "newsletter",
+    "promo",
+    "related",
+    "share",
+    "sidebar",
+    "social",
+    "sponsor",
+    "subscribe",
+    "widget",
+];
+
+// paragraphs shorter than this are captions, credits and UI labels
+const MIN_PARAGRAPH_LEN: usize = 25;
+const MAX_LINK_DENSITY: f32 = 0.5;
+// siblings of the best container scoring this share of it are kept with it
+const SIBLING_SCORE_RATIO: f32 = 0.2;
+const MIN_SIBLING_SCORE: f32 = 10.0;
+// paragraphs needed for full marks on the length part of the confidence
+const CONFIDENT_PARAGRAPHS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentSource {
+    Selectors,
+    Readability,
+}
+
+// How an article's paragraphs were found, and how sure we are they are the
+// story. Selector matches are trusted outright.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ContentExtraction {
+    pub source: ContentSource,
+    pub confidence: f32,
+}
+
+impl ContentExtraction {
+    pub fn selectors() -> Self {
+        ContentExtraction {
+            source: ContentSource::Selectors,
+            confidence: 1.0,
+        }
+    }
+}
+
+fn element_text(elem: ElementRef) -> String {
+    elem.text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn link_density(elem: ElementRef, text_len: usize) -> f32 {
+    let link_selector = Selector::parse("a").expect("Unable to construct selector.");
+    let link_len: usize = elem
+        .select(&link_selector)
+        .map(|link| element_text(link).len())
+        .sum();
+    link_len as f32 / text_len.max(1) as f32
+}
+
+fn has_hint(names: &[String], hints: &[&str]) -> bool {
+    names.iter().any(|name| {
+        hints.iter().any(|hint| {
+            if hint.ends_with('-') {
+                name.starts_with(hint)
+            } else {
+                name.contains(hint)
+            }
+        })
+    })
+}
+
+fn class_weight(elem: ElementRef) -> f32 {
+    let value = elem.value();
+    let names: Vec<String> = value
+        .classes()
+        .chain(value.id())
+        .map(str::to_lowercase)
+        .collect();
+
+    let mut weight = 0.0;
+    if has_hint(&names, &NEGATIVE_HINTS) {
+        weight -= 25.0;
+    }
+    if has_hint(&names, &POSITIVE_HINTS) {
+        weight += 25.0;
+    }
+    weight
+}
+
+// The walk up from a paragraph stops at `container` or at the first block
+// hinted as content, so a negative hint on a page-wide wrapper (a layout "with
+// sidebar", say) doesn't drop the story inside it.
+fn is_boilerplate(elem: ElementRef, container: Option<ElementRef>) -> bool {
+    for ancestor in elem.ancestors().filter_map(ElementRef::wrap) {
+        if container.is_some_and(|container| container.id() == ancestor.id()) {
+            return false;
+        }
+        if SKIPPED_TAGS.contains(&ancestor.value().name()) {
+            return true;
+        }
+        let weight = class_weight(ancestor);
+        if weight != 0.0 {
+            return weight < 0.0;
+        }
+    }
+    false
+}
+
+fn content_paragraphs(container: ElementRef, normalizer: &TextNormalizer) -> Vec<Paragraph> {
+    let par_selector = Selector::parse("p").expect("Unable to construct selector.");
+    container
+        .select(&par_selector)
+        .filter(|par| !is_boilerplate(*par, Some(container)))
+        .filter_map(|par| {
+            let paragraph = normalizer.paragraph(par)?;
+            let text_len = paragraph.text.len();
+            let long_enough = text_len >= MIN_PARAGRAPH_LEN;
+            (long_enough && link_density(par, text_len) < MAX_LINK_DENSITY).then_some(paragraph)
+        })
+        .collect()
+}
+
+// Readability-style scoring: every paragraph scores its parent in full and its
+// grandparent by half, by length and comma count. Containers are then
+// weighted by class/id hints and penalised for link-heavy text, and the best
+// one holds the story.
+//
+// The confidence blends how much of the page's paragraph text the winner
+// holds, how far it is ahead of the runner-up, and how many paragraphs it has.
+pub fn extract_main_content(
+    doc: &Html,
+    normalizer: &TextNormalizer,
+) -> Option<(Vec<Paragraph>, ContentExtraction)> {
+    let par_selector = Selector::parse("p").expect("Unable to construct selector.");
+    let mut scores = HashMap::new();
+    let mut total_len = 0;
+
+    for par in doc.select(&par_selector) {
+        if is_boilerplate(par, None) {
+            continue;
+        }
+        let text = element_text(par);
+        if text.len() < MIN_PARAGRAPH_LEN {
+            continue;
+        }
+        total_len += text.len();
+
+        let score = 1.0 + text.matches(',').count() as f32 + (text.len() as f32 / 100.0).min(3.0);
+        let mut ancestors = par.ancestors().filter_map(ElementRef::wrap);
+        if let Some(parent) = ancestors.next() {
+            *scores.entry(parent.id()).or_insert(class_weight(parent)) += score;
+        }
+        if let Some(grandparent) = ancestors.next() {
+            *scores
+                .entry(grandparent.id())
+                .or_insert(class_weight(grandparent)) += score / 2.0;
+        }
+    }
+
+    let mut candidates: Vec<(ElementRef, f32)> = scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let elem = ElementRef::wrap(doc.tree.get(id)?)?;
+            let text_len = element_text(elem).len();
+            Some((elem, score * (1.0 - link_density(elem, text_len))))
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let (best, best_score) = *candidates.first()?;
+    if best_score <= 0.0 {
+        return None;
+    }
+    // stories split across sibling blocks (between ads, say) are kept whole
+    let threshold = (best_score * SIBLING_SCORE_RATIO).max(MIN_SIBLING_SCORE);
+    let score_of = |elem: ElementRef| {
+        candidates
+            .iter()
+            .find(|(candidate, _)| candidate.id() == elem.id())
+            .map(|(_, score)| *score)
+    };
+    let containers: Vec<ElementRef> = match best.parent() {
+        Some(parent) => parent
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|sibling| {
+                sibling.id() == best.id()
+                    || score_of(*sibling).is_some_and(|score| score >= threshold)
+            })
+            .collect(),
+        None => vec![best],
+    };
+    let paragraphs: Vec<Paragraph> = containers
+        .iter()
+        .flat_map(|c| content_paragraphs(*c, normalizer))
+        .collect();
+    if paragraphs.is_empty() {
+        return None;
+    }
+
+    let kept_len: usize = paragraphs
+        .iter()
+        .map(|paragraph| paragraph.text.len())
+        .sum();
+    let coverage = (kept_len as f32 / total_len.max(1) as f32).min(1.0);
+    // the containers' own parents and children score from the same paragraphs
+    let related = |elem: ElementRef| {
+        containers.iter().any(|container| {
+            container.id() == elem.id()
+                || container.ancestors().any(|node| node.id() == elem.id())
+                || elem.ancestors().any(|node| node.id() == container.id())
+        })
+    };
+    let runner_up = candidates
+        .iter()
+        .find(|(elem, _)| !related(*elem))
+        .map_or(0.0, |(_, score)| score.max(0.0));
+    let margin = (1.0 - runner_up / best_score).max(0.0);
+    let length = (paragraphs.len() as f32 / CONFIDENT_PARAGRAPHS as f32).min(1.0);
+
+    let extraction = ContentExtraction {
+        source: ContentSource::Readability,
+        confidence: 0.4 * coverage + 0.3 * margin + 0.3 * length,
+    };
+    Some((paragraphs, extraction))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::TextRules;
+
+    const STORY: [&str; 5] = [
+        "The city will close the bridge for repairs next month, officials said on Tuesday.",
+        "Drivers will be sent along the river road, which is expected to add about ten minutes, at rush hour, to most trips.",
+        "Cyclists and pedestrians can still cross on the east side, though the path will be narrowed.",
+        "The work, which was delayed by a year, is meant to fix cracks found in the deck in 2021.",
+        "Council approved the contract in January after a long debate about its cost.",
+    ];
+
+    fn page(wrapper_class: &str) -> String {
+        let story: String = STORY.iter().map(|par| format!("<p>{par}</p>")).collect();
+        format!(
+            r#"<html><body><div class="{wrapper_class}">
+<nav><p>Home, News, Sports, Business, Opinion, Weather, Local, Video</p></nav>
+<div class="story-body">{story}</div>
+<div class="related-links">
+  <p><a href="/a">Bridge repairs delayed again, council says</a></p>
+  <p><a href="/b">What the new transit plan means for your commute</a></p>
+</div>
+<div class="briefing"><p>Get the day's top stories in your inbox every morning.</p></div>
+<div class="comments">
+  <p>I drive this way every day, and the detour is going to be a nightmare.</p>
+  <p>Finally! That bridge has needed work for years, glad to see it done.</p>
+</div>
+</div></body></html>"#
+        )
+    }
+
+    fn extract(html: &str) -> (Vec<String>, ContentExtraction) {
+        let doc = Html::parse_document(html);
+        let (paragraphs, extraction) =
+            extract_main_content(&doc, &TextNormalizer::new(&TextRules::default())).unwrap();
+        let texts = paragraphs
+            .into_iter()
+            .map(|paragraph| paragraph.text)
+            .collect();
+        (texts, extraction)
+    }
+
+    #[test]
+    fn picks_the_story_over_nav_comments_and_related_links() {
+        let (paragraphs, extraction) = extract(&page("layout"));
+        assert_eq!(paragraphs, STORY);
+        assert_eq!(extraction.source, ContentSource::Readability);
+        // only the newsletter blurb is scored besides the story, and it is
+        // far behind
+        assert!(
+            (extraction.confidence - 0.946).abs() < 0.001,
+            "{extraction:?}"
+        );
+    }
+
+    #[test]
+    fn negative_hints_on_a_page_wrapper_keep_the_story() {
+        let (paragraphs, extraction) = extract(&page("layout layout--with-sidebar"));
+        assert_eq!(paragraphs, STORY);
+        // unhinted blocks inside the wrapper, like the blurb, are dropped with it
+        assert_eq!(extraction.confidence, 1.0);
+    }
+
+    #[test]
+    fn a_short_story_is_less_certain() {
+        let html = r#"<html><body>
+<div class="story-body"><p>The city will close the bridge for repairs next month, officials said.</p></div>
+<div class="comments"><p>I drive this way every day, and the detour is going to be a nightmare.</p></div>
+</body></html>"#;
+        let (paragraphs, extraction) = extract(html);
+        assert_eq!(paragraphs.len(), 1);
+        // full coverage and margin, but one paragraph of the five for full marks
+        assert!(
+            (extraction.confidence - 0.76).abs() < 0.001,
+            "{extraction:?}"
+        );
+    }
+}
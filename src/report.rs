@@ -0,0 +1,124 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::crawl_state::FetchOutcome;
+use crate::errors::{ErrorCategory, ScrapeError};
+
+// the JSON report lists every failure; the printed summary only the first few
+const MAX_SUMMARY_URLS: usize = 5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Failure {
+    pub url: String,
+    pub category: ErrorCategory,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new(url: &str, err: &anyhow::Error) -> Self {
+        Failure {
+            url: url.to_string(),
+            category: ScrapeError::classify(err),
+            message: format!("{err:#}"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct SiteReport {
+    pub new: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub skipped: usize,
+    pub duplicates: usize,
+    pub failed: usize,
+    pub failures: Vec<Failure>,
+}
+
+impl SiteReport {
+    pub fn add(&mut self, outcome: FetchOutcome) {
+        match outcome {
+            FetchOutcome::New => self.new += 1,
+            FetchOutcome::Updated => self.updated += 1,
+            FetchOutcome::Unchanged => self.unchanged += 1,
+            FetchOutcome::Skipped => self.skipped += 1,
+            FetchOutcome::Duplicate => self.duplicates += 1,
+        }
+    }
+
+    pub fn add_failure(&mut self, failure: Failure) {
+        self.failed += 1;
+        self.failures.push(failure);
+    }
+
+    pub fn failures_by_category(&self) -> BTreeMap<ErrorCategory, Vec<&Failure>> {
+        let mut categories: BTreeMap<ErrorCategory, Vec<&Failure>> = BTreeMap::new();
+        for failure in &self.failures {
+            categories
+                .entry(failure.category)
+                .or_default()
+                .push(failure);
+        }
+        categories
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    pub started: DateTime<Utc>,
+    pub finished: DateTime<Utc>,
+    pub sites: BTreeMap<String, SiteReport>,
+}
+
+impl RunReport {
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        for (name, report) in &self.sites {
+            writeln!(
+                summary,
+                "{name}: {} new, {} updated, {} failed ({} unchanged, {} skipped, {} duplicates)",
+                report.new,
+                report.updated,
+                report.failed,
+                report.unchanged,
+                report.skipped,
+                report.duplicates
+            )
+            .expect("Writing to a String cannot fail.");
+
+            for (category, failures) in report.failures_by_category() {
+                writeln!(summary, "  {} ({})", category.name(), failures.len())
+                    .expect("Writing to a String cannot fail.");
+                for failure in failures.iter().take(MAX_SUMMARY_URLS) {
+                    writeln!(summary, "    {}", failure.url)
+                        .expect("Writing to a String cannot fail.");
+                }
+                if failures.len() > MAX_SUMMARY_URLS {
+                    writeln!(
+                        summary,
+                        "    ... and {} more",
+                        failures.len() - MAX_SUMMARY_URLS
+                    )
+                    .expect("Writing to a String cannot fail.");
+                }
+            }
+        }
+        summary
+    }
+
+    // Saved as `<dir>/<start time>.json`, so reports from earlier runs are kept.
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.json", self.started.format("%Y-%m-%dT%H%M%SZ")));
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
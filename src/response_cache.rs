@@ -0,0 +1,137 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Responses are stored content-addressed: bodies live under `bodies/<sha256 of body>`
+// and `entries/<sha256 of url>.json` records the validators and which body the URL
+// last returned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body_hash: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+pub struct ResponseCache {
+    root: PathBuf,
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+// Numbers temporary files, so concurrent tasks writing the same path never
+// share one.
+static NEXT_TMP: AtomicU64 = AtomicU64::new(0);
+
+// Writes through a temporary file so concurrent readers never see a partial file.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp_path = path.with_extension(format!(
+        "tmp{}-{}",
+        std::process::id(),
+        NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+impl ResponseCache {
+    pub fn new(root: &Path) -> Result<Self> {
+        fs::create_dir_all(root.join("entries"))?;
+        fs::create_dir_all(root.join("bodies"))?;
+        Ok(ResponseCache {
+            root: root.to_path_buf(),
+        })
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        self.root
+            .join("entries")
+            .join(format!("{}.json", sha256_hex(url.as_bytes())))
+    }
+
+    fn body_path(&self, body_hash: &str) -> PathBuf {
+        self.root.join("bodies").join(body_hash)
+    }
+
+    pub fn entry(&self, url: &str) -> Option<CacheEntry> {
+        let text = fs::read_to_string(self.entry_path(url)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&text).ok()?;
+        // an entry whose body went missing is as good as no entry
+        if self.body_path(&entry.body_hash).exists() {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    pub fn body(&self, entry: &CacheEntry) -> Result<String> {
+        Ok(fs::read_to_string(self.body_path(&entry.body_hash))?)
+    }
+
+    pub fn store(
+        &self,
+        url: &str,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        body: &str,
+    ) -> Result<()> {
+        let body_hash = sha256_hex(body.as_bytes());
+        let body_path = self.body_path(&body_hash);
+        if !body_path.exists() {
+            write_atomic(&body_path, body.as_bytes())?;
+        }
+
+        let entry = CacheEntry {
+            url: String::from(url),
+            etag,
+            last_modified,
+            body_hash,
+            fetched_at: Utc::now(),
+        };
+        write_atomic(
+            &self.entry_path(url),
+            serde_json::to_string_pretty(&entry)?.as_bytes(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_writes_to_one_path_never_mix() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("entry.json");
+        let bodies: Vec<String> = (0..8).map(|i| i.to_string().repeat(100_000)).collect();
+
+        thread::scope(|scope| {
+            for body in &bodies {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..10 {
+                        write_atomic(path, body.as_bytes()).unwrap();
+                    }
+                });
+            }
+        });
+
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(bodies.contains(&written));
+        // no temporary files are left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::crawl_state::CrawlState;
+use crate::site_config::load_config;
+
+// A paragraph-level edit between two revisions of an article. Indexes refer to
+// the old revision for removals and the new revision for additions; paragraphs
+// kept in both are left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ParagraphChange {
+    Added { index: usize, text: String },
+    Removed { index: usize, text: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct HeadlineChange {
+    pub canonical_url: String,
+    pub revision: u32,
+    pub fetched_at: String,
+    pub old_title: String,
+    pub new_title: String,
+}
+
+// Longest-common-subsequence diff, which is plenty fast for the few dozen
+// paragraphs in a news article.
+pub fn diff_paragraphs(old: &[String], new: &[String]) -> Vec<ParagraphChange> {
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || common[i][j + 1] >= common[i + 1][j]) {
+            changes.push(ParagraphChange::Added {
+                index: j,
+                text: new[j].clone(),
+            });
+            j += 1;
+        } else {
+            changes.push(ParagraphChange::Removed {
+                index: i,
+                text: old[i].clone(),
+            });
+            i += 1;
+        }
+    }
+    changes
+}
+
+pub fn report_headline_changes(config_path: &Path) -> Result<()> {
+    let config = load_config(config_path)?;
+    let state = CrawlState::open(Path::new(&config.crawler.state_path))?;
+
+    for change in state.headline_changes()? {
+        println!(
+            "{} (revision {}, {})\n  - {}\n  + {}",
+            change.canonical_url,
+            change.revision,
+            change.fetched_at,
+            change.old_title,
+            change.new_title
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paragraphs(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    #[test]
+    fn unchanged_articles_have_no_diff() {
+        let old = paragraphs(&["One.", "Two."]);
+        assert!(diff_paragraphs(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn edits_are_a_removal_and_an_addition() {
+        let old = paragraphs(&["One.", "Two.", "Three."]);
+        let new = paragraphs(&["One.", "Two, corrected.", "Three.", "Update: Four."]);
+        assert_eq!(
+            diff_paragraphs(&old, &new),
+            [
+                ParagraphChange::Added {
+                    index: 1,
+                    text: "Two, corrected.".into()
+                },
+                ParagraphChange::Removed {
+                    index: 1,
+                    text: "Two.".into()
+                },
+                ParagraphChange::Added {
+                    index: 3,
+                    text: "Update: Four.".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn indexes_refer_to_their_own_revision() {
+        let old = paragraphs(&["Lede.", "Old quote.", "Body."]);
+        let new = paragraphs(&["New lede.", "Lede.", "Body."]);
+        assert_eq!(
+            diff_paragraphs(&old, &new),
+            [
+                ParagraphChange::Added {
+                    index: 0,
+                    text: "New lede.".into()
+                },
+                ParagraphChange::Removed {
+                    index: 1,
+                    text: "Old quote.".into()
+                },
+            ]
+        );
+        assert_eq!(
+            diff_paragraphs(&[], &paragraphs(&["Only."])),
+            [ParagraphChange::Added {
+                index: 0,
+                text: "Only.".into()
+            }]
+        );
+    }
+}
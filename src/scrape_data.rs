@@ -1,268 +1,833 @@
 use std::{
-    fs::{create_dir_all, File},
-    io::Write,
+    collections::{BTreeMap, HashSet},
     path::Path,
+    sync::{Arc, Mutex},
 };
 
 use anyhow::{anyhow, Result};
-use regex::Regex;
-use reqwest::blocking::get;
+use chrono::{Duration, Utc};
+use reqwest::Url;
 use scraper::{ElementRef, Html, Selector};
 
-#[derive(Debug)]
-struct Article {
-    title: String,
-    contents: Vec<String>,
-    date: String,
-}
-
-fn get_date_from_timestamp(doc: &Html) -> Result<String> {
-    let time_selector = Selector::parse("time.timeStamp").expect("Unable to create selector");
-    let time_elem = doc.select(&time_selector).next();
+use crate::article::{content_hash, Article};
+use crate::crawl_state::{CrawlState, FetchOutcome};
+use crate::discovery::{merge_links, normalize_url, resolve_link, DiscoveredLink, LinkSource};
+use crate::extractors::{split_bylines, ExtractorRegistry, SiteExtractor};
+use crate::feeds::parse_feed;
+use crate::fetcher::Fetcher;
+use crate::language::detect_language;
+use crate::paywall::check_paywall;
+use crate::report::{Failure, RunReport, SiteReport};
+use crate::site_config::{load_config, NewsSite, Pagination, ScrapeConfig};
+use crate::sitemaps::{parse_sitemap, DateRange, Sitemap};
+use crate::storage::ArticleStore;
+use crate::text::split_links;
+use crate::wire::wire_story;
 
-    if let Some(time_elem) = time_elem {
-        let date_regex = Regex::new(r"(?P<y>\d{4})-(?P<m>\d{2})-(?P<d>\d{2}).*")?;
-        let pub_date = time_elem
-            .value()
-            .attr("datetime")
-            .ok_or(anyhow!("Cannot find"))?;
-        let pub_date = date_regex.replace_all(pub_date, "$d-$m-$y");
+fn get_article_elem(doc: &Html) -> Result<ElementRef> {
+    let article_selector =
+        Selector::parse("article.article-content-story").expect("Unable to construct selector.");
+    let article = doc.select(&article_selector).next();
 
-        Ok(String::from(pub_date))
-    } else {
-        Err(anyhow!("Cannot find timestamp."))?
+    match article {
+        Some(article) => Ok(article),
+        None => Err(anyhow!("Cannot find article element")),
     }
 }
 
-fn get_date_from_div(doc: &Html) -> Result<String> {
-    let date_selector =
-        Selector::parse("span.published-date__since").expect("Unable to create selector");
-    let date_elem = doc.select(&date_selector).next();
-
-    if let Some(date_elem) = date_elem {
-        let date_regex =
-            Regex::new(r"Published (?P<m>[A-Za-z]{3}) (?P<d>\d\d?), (?P<y>\d{4})").unwrap();
-        let pub_date = date_elem.text().collect::<Vec<_>>()[0];
-        let pub_date = date_regex.replace_all(pub_date, "$d-$m-$y");
-        let pub_date = pub_date.replace("Mar", "3");
+fn clean_title(title: &str, title_suffixes: &[String]) -> String {
+    let mut title = String::from(title);
+    for suffix in title_suffixes {
+        title = title.replace(suffix, "");
+    }
+    title.trim().to_string()
+}
 
-        Ok(pub_date.to_string())
+fn extract_article(
+    link: &DiscoveredLink,
+    article_text: &str,
+    site: &NewsSite,
+    extractor: &dyn SiteExtractor,
+) -> Result<Article> {
+    let doc = Html::parse_document(article_text);
+    let href = &link.url;
+
+    // Structured metadata wins, then the page's own markup, and feed
+    // metadata fills in for pages missing a title or date.
+    let metadata = extractor.metadata(&doc, site);
+    let title_text = match (metadata.headline, extractor.title(&doc, site), &link.title) {
+        (Some(headline), _, _) => headline,
+        (None, Ok(title), _) => title,
+        (None, Err(_), Some(title)) => title.clone(),
+        (None, Err(err), None) => return Err(err),
+    };
+    let pub_date = match (
+        metadata.published,
+        extractor.date(&doc, site),
+        link.published,
+    ) {
+        (Some(date), _, _) => date,
+        (None, Ok(date), _) => date,
+        (None, Err(_), Some(date)) => date,
+        (None, Err(err), None) => return Err(err),
+    };
+    let (paragraphs, extraction) = extractor.content(&doc, site)?;
+    let (paragraphs, mut links) = split_links(paragraphs);
+    for link in &mut links {
+        if let Some(resolved) = resolve_link(href, &link.href) {
+            link.href = resolved;
+        }
+    }
+    let bylines = if metadata.authors.is_empty() {
+        extractor.authors(&doc, site)
     } else {
-        Err(anyhow!("Cannot find date."))?
+        metadata.authors
+    };
+    let mut authors = split_bylines(&bylines);
+    // wire copy credited only in a dateline or sign-off still gets a byline
+    let wire = wire_story(&authors, &paragraphs);
+    if let Some(wire) = &wire {
+        let credit = wire.service.name();
+        if !authors
+            .iter()
+            .any(|author| author.eq_ignore_ascii_case(credit))
+        {
+            authors.push(credit.to_string());
+        }
     }
+    let paywall = check_paywall(
+        &doc,
+        metadata.accessible_for_free,
+        &site.paywall_selectors,
+        &paragraphs,
+    );
+    let canonical_url = extractor
+        .canonical_url(&doc, site)
+        .or(metadata.url)
+        .and_then(|canonical| resolve_link(href, &canonical))
+        .unwrap_or_else(|| href.clone());
+
+    Ok(Article {
+        url: href.clone(),
+        canonical_url: normalize_url(&canonical_url).unwrap_or(canonical_url),
+        guid: link.guid.clone(),
+        site: site.name.clone(),
+        title: clean_title(&title_text, &site.title_suffixes),
+        subtitle: metadata
+            .description
+            .or_else(|| extractor.subtitle(&doc, site)),
+        wire,
+        authors,
+        section: metadata
+            .section
+            .or_else(|| extractor.section(&doc, site, href)),
+        tags: metadata.tags,
+        image: metadata.image.and_then(|image| resolve_link(href, &image)),
+        published: pub_date,
+        updated: metadata.modified.or_else(|| extractor.updated(&doc, site)),
+        scraped: Utc::now(),
+        language: extractor.language(&doc, site).or(metadata.language),
+        detected_language: detect_language(&paragraphs),
+        content_hash: content_hash(&paragraphs),
+        paragraphs,
+        extraction: Some(extraction),
+        links,
+        paywall: paywall.signals,
+        partial: paywall.partial,
+    })
 }
 
-fn get_article_from_span(doc: &Html) -> Result<String> {
-    let date_selector =
-        Selector::parse("span.article__published-date").expect("Unable to create selector");
-    let date_elem = doc.select(&date_selector).next();
+async fn parse_article(
+    link: &DiscoveredLink,
+    site: &NewsSite,
+    extractor: &dyn SiteExtractor,
+    fetcher: &Fetcher,
+) -> Result<Article> {
+    // make request
+    let article_text = fetcher.get_text(&link.url).await?;
+    extract_article(link, &article_text, site, extractor)
+}
 
-    if let Some(date_elem) = date_elem {
-        let date_regex = Regex::new(r"...\., (?P<m>[A-Za-z]+) (?P<d>\d\d?), (?P<y>\d{4})").unwrap();
-        let pub_date = date_elem.text().collect::<Vec<_>>()[0];
-        let pub_date = date_regex.replace_all(pub_date, "$d-$m-$y");
+// Returns the article links on a section page and, for next-link pagination,
+// the URL of the following page.
+fn read_section_page(
+    page_href: &str,
+    text: &str,
+    site: &NewsSite,
+    extractor: &dyn SiteExtractor,
+) -> (Vec<String>, Option<String>) {
+    let doc = Html::parse_document(text);
+    let links = extractor.links(&doc, site);
+
+    let next_href = match &site.pagination {
+        Some(Pagination::NextLink { selector }) => {
+            let next_selector = Selector::parse(selector).expect("Unable to construct selector.");
+            doc.select(&next_selector)
+                .find_map(|elem| elem.value().attr("href"))
+                .and_then(|href| Url::parse(page_href).ok()?.join(href).ok())
+                .map(String::from)
+        }
+        _ => None,
+    };
 
-        Ok(pub_date.to_string())
-    } else {
-        Err(anyhow!("Cannot find date."))?
-    }
+    (links, next_href)
 }
 
-fn get_story_div(doc: &Html, story_attr: String) -> Result<ElementRef> {
-    let story_selector = Selector::parse(&story_attr).expect("Unable to construct selector.");
-    let story = doc.select(&story_selector).next();
+fn read_json_page(
+    text: &str,
+    site: &NewsSite,
+    links_pointer: &str,
+    url_field: &str,
+) -> Result<Vec<String>> {
+    let value: serde_json::Value = serde_json::from_str(text)?;
+    let items = value
+        .pointer(links_pointer)
+        .and_then(|items| items.as_array())
+        .ok_or(anyhow!("No item array at {links_pointer:?}"))?;
+
+    let base = format!("https://{}/", site.link_prefix);
+    Ok(items
+        .iter()
+        .filter_map(|item| item.get(url_field)?.as_str())
+        .filter_map(|href| resolve_link(&base, href))
+        .collect())
+}
 
-    match story {
-        Some(story) => Ok(story),
-        None => Err(anyhow!("Cannot find story div")),
+async fn get_section_links(
+    section_href: &str,
+    site: &NewsSite,
+    extractor: &dyn SiteExtractor,
+    fetcher: &Fetcher,
+) -> Result<Vec<String>> {
+    let text = fetcher.get_text(section_href).await?;
+    let (mut links, mut next_href) = read_section_page(section_href, &text, site, extractor);
+
+    for page in 2..=site.max_pages {
+        let page_links = match &site.pagination {
+            Some(Pagination::NextLink { .. }) => {
+                let Some(page_href) = next_href.take() else {
+                    break;
+                };
+                let text = fetcher.get_text(&page_href).await?;
+                let (page_links, page_next) = read_section_page(&page_href, &text, site, extractor);
+                next_href = page_next;
+                page_links
+            }
+            Some(Pagination::Template { template }) => {
+                let page_href = Pagination::page_url(template, section_href, page);
+                let text = fetcher.get_text(&page_href).await?;
+                read_section_page(&page_href, &text, site, extractor).0
+            }
+            Some(Pagination::Json {
+                template,
+                links_pointer,
+                url_field,
+            }) => {
+                let page_href = Pagination::page_url(template, section_href, page);
+                let text = fetcher.get_text(&page_href).await?;
+                read_json_page(&text, site, links_pointer, url_field)?
+            }
+            None => break,
+        };
+
+        // a page with nothing new means we've run off the end of the section
+        let n_before = links.len();
+        for link in page_links {
+            if !links.contains(&link) {
+                links.push(link);
+            }
+        }
+        if links.len() == n_before {
+            break;
+        }
     }
+
+    Ok(links)
 }
 
-fn get_article_elem(doc: &Html) -> Result<ElementRef> {
-    let article_selector =
-        Selector::parse("article.article-content-story").expect("Unable to construct selector.");
-    let article = doc.select(&article_selector).next();
+async fn get_html_links(
+    site: &NewsSite,
+    extractor: &dyn SiteExtractor,
+    fetcher: &Fetcher,
+    failures: &mut Vec<Failure>,
+) -> Result<Vec<DiscoveredLink>> {
+    let mut links = Vec::new();
+    let mut last_err = None;
+
+    for section_href in std::iter::once(&site.news_href).chain(&site.sections) {
+        match get_section_links(section_href, site, extractor, fetcher).await {
+            Ok(section_links) => links.extend(section_links),
+            Err(err) => {
+                println!("Cannot read section {section_href}: {err}");
+                failures.push(Failure::new(section_href, &err));
+                last_err = Some(err);
+            }
+        }
+    }
 
-    match article {
-        Some(article) => Ok(article),
-        None => Err(anyhow!("Cannot find article element")),
+    match (links.is_empty(), last_err) {
+        (true, Some(err)) => Err(err),
+        _ => Ok(links.into_iter().map(DiscoveredLink::from_html).collect()),
     }
 }
 
-fn parse_article(href: &str, site: &NewsSite) -> Result<Article> {
-    // make request
-    let request = get(href);
-    let article_text = match request {
-        Ok(res) => res.text().expect("Unable to get request text."),
-        Err(error) => return Err(anyhow!(error)),
-    };
+async fn get_feed_links(feed_href: &str, fetcher: &Fetcher) -> Result<Vec<DiscoveredLink>> {
+    let text = fetcher.get_text(feed_href).await?;
+    parse_feed(&text)
+}
 
-    let doc = Html::parse_document(&article_text);
+const MAX_SITEMAP_DEPTH: usize = 3;
+
+// Walks sitemap indexes breadth-first, skipping child sitemaps last modified
+// before the backfill range and pages published outside it. Sitemaps that
+// can't be read are added to `failures` and skipped.
+async fn get_sitemap_links(
+    site: &NewsSite,
+    fetcher: &Fetcher,
+    backfill: &DateRange,
+    failures: &mut Vec<Failure>,
+) -> Vec<DiscoveredLink> {
+    let mut pending: Vec<String> = site.sitemaps.clone();
+    if site.robots_sitemaps {
+        match fetcher.sitemaps_for(&site.news_href).await {
+            Ok(sitemaps) => pending.extend(sitemaps),
+            Err(err) => {
+                println!("Cannot read robots.txt sitemaps for {}: {err}", site.name);
+                failures.push(Failure::new(&site.news_href, &err));
+            }
+        }
+    }
 
-    // get title
-    let title_selector = Selector::parse("title").expect("unable to construct selector.");
-    let title_elem = doc.select(&title_selector).next();
-    if let None = title_elem {
-        return Err(anyhow!("Couldn't find title."));
+    let mut links = Vec::new();
+    for _ in 0..MAX_SITEMAP_DEPTH {
+        let mut next = Vec::new();
+        for sitemap_href in pending {
+            let text = match fetcher.get_text(&sitemap_href).await {
+                Ok(text) => text,
+                Err(err) => {
+                    println!("Cannot read sitemap {sitemap_href}: {err}");
+                    failures.push(Failure::new(&sitemap_href, &err));
+                    continue;
+                }
+            };
+            match parse_sitemap(&text) {
+                Ok(Sitemap::Index(entries)) => next.extend(
+                    entries
+                        .into_iter()
+                        .filter(|entry| backfill.may_contain(entry.lastmod))
+                        .map(|entry| entry.url),
+                ),
+                Ok(Sitemap::UrlSet(urls)) => links.extend(
+                    urls.into_iter()
+                        .filter(|link| backfill.contains(link.published)),
+                ),
+                Err(err) => {
+                    println!("Cannot parse sitemap {sitemap_href}: {err}");
+                    failures.push(Failure::new(&sitemap_href, &err));
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        pending = next;
     }
-    let title_text = title_elem.unwrap().text().collect::<Vec<_>>()[0];
-
-    // get date
-    let mut pub_date = String::new();
-    if let Ok(date) = get_date_from_timestamp(&doc) {
-        pub_date = date;
-    } else if let Ok(date) = get_date_from_div(&doc) {
-        pub_date = date;
-    } else if let Ok(date) = get_article_from_span(&doc) {
-        pub_date = date;
-    } else {
-        return Err(anyhow!("Couldn't find timestamp."));
+
+    links
+}
+
+// Feeds come first so their titles and dates win when a link shows up in several sources.
+// A failing feed, section page or sitemap is added to `failures` and skipped,
+// and only fails the whole site when no source yields anything.
+pub async fn get_news_links(
+    site: &NewsSite,
+    extractor: &dyn SiteExtractor,
+    fetcher: &Fetcher,
+    backfill: &DateRange,
+    failures: &mut Vec<Failure>,
+) -> Result<Vec<DiscoveredLink>> {
+    let mut sources = Vec::new();
+    let mut last_err = None;
+
+    for feed in &site.feeds {
+        match get_feed_links(feed, fetcher).await {
+            Ok(links) => sources.push(links),
+            Err(err) => {
+                println!("Cannot read feed {feed}: {err}");
+                failures.push(Failure::new(feed, &err));
+                last_err = Some(err);
+            }
+        }
+    }
+    if !site.sitemaps.is_empty() || site.robots_sitemaps {
+        sources.push(get_sitemap_links(site, fetcher, backfill, failures).await);
+    }
+    if site.html_discovery {
+        match get_html_links(site, extractor, fetcher, failures).await {
+            Ok(links) => sources.push(links),
+            Err(err) => last_err = Some(err),
+        }
     }
 
-    // get article contents
-    let mut contents = Vec::new();
-
-    if let Ok(elem) = get_story_div(&doc, site.body.clone()) {
-        let story = elem;
-
-        // get p elements
-        let par_selector = Selector::parse(&site.par_class).expect("Unable to construct selector.");
-        for par in story.select(&par_selector) {
-            let par_text = par.text().collect::<Vec<_>>();
-            if par_text.len() > 1 {
-                let full_text = par_text.join("\n");
-                let full_text = full_text.replace("Article content", "");
-                let full_text = full_text.replace("\n ", "");
-                contents.push(full_text.to_string());
-            } else if par.inner_html().len() > 0 {
-                let full_text = par.inner_html();
-                let full_text = full_text.replace("Article content", "");
-                let full_text = full_text.replace("\n ", "");
-                contents.push(full_text.to_string());
+    match (sources.is_empty(), last_err) {
+        (true, Some(err)) => Err(err),
+        _ => {
+            let mut links = merge_links(sources);
+            if let Some(max_articles) = site.max_articles {
+                links.truncate(max_articles);
             }
+            Ok(links)
         }
-    } else {
-        return Err(anyhow!("Couldn't find article body."));
     }
+}
 
-    Ok(Article {
-        title: String::from(title_text),
-        contents: contents,
-        date: String::from(pub_date),
-    })
+// State shared by every scraping task in a run.
+struct ScrapeContext {
+    registry: ExtractorRegistry,
+    fetcher: Fetcher,
+    state: CrawlState,
+    store: ArticleStore,
+    backfill: DateRange,
+    // articles younger than this are refetched on every run
+    refetch_window: Duration,
+    // normalized URLs of every article claimed by a task this run
+    claimed: Mutex<HashSet<String>>,
 }
 
-fn save_article(article: &Article, path_root: &Path) {
-    let date_dir = path_root.join(&article.date);
-    if !date_dir.exists() {
-        create_dir_all(&date_dir).expect("Cannot create directory at {date_dir}.");
+impl ScrapeContext {
+    // Returns false if another task already has this article.
+    fn claim(&self, url: &str) -> bool {
+        let key = normalize_url(url).unwrap_or_else(|| url.to_string());
+        self.claimed.lock().unwrap().insert(key)
     }
+}
 
-    let filename = String::from(&article.title)
-        .replace(" | CBC News", "")
-        .replace(" | National Post", "")
-        .replace(" | The Star", "")
-        .replace(" ", "_")
-        .to_lowercase();
-    let filename = format!("{filename}.txt");
-    let output_path = date_dir.join(filename);
-
-    let output_file = File::create(output_path);
-    if let Ok(mut file) = output_file {
-        for par in &article.contents {
-            file.write_all(par.as_bytes());
-            file.write_all(b"\n\n");
+async fn scrape_link(
+    link: DiscoveredLink,
+    site: &NewsSite,
+    ctx: &ScrapeContext,
+) -> Result<FetchOutcome> {
+    if !ctx.state.should_fetch(&link, ctx.refetch_window)? {
+        return Ok(FetchOutcome::Skipped);
+    }
+    if !ctx.claim(&link.url) {
+        ctx.state.record_duplicate(&site.name, &link.url, None)?;
+        return Ok(FetchOutcome::Duplicate);
+    }
+
+    let extractor = ctx.registry.get(&site.name);
+    let article = parse_article(&link, site, extractor, &ctx.fetcher).await?;
+    // different links can still turn out to be the same page once fetched
+    let link_key = normalize_url(&link.url);
+    if link_key.as_ref() != Some(&article.canonical_url) && !ctx.claim(&article.canonical_url) {
+        ctx.state
+            .record_duplicate(&site.name, &link.url, Some(&article.canonical_url))?;
+        return Ok(FetchOutcome::Duplicate);
+    }
+
+    if let Some(wire) = &article.wire {
+        for (other_site, other_url) in ctx.state.record_wire_story(&site.name, &article, wire)? {
+            println!(
+                "{} story {} was also published by {other_site} at {other_url}",
+                wire.service.name(),
+                article.canonical_url
+            );
+        }
+    }
+
+    // Revisions are keyed by canonical URL, so an edited story replaces its
+    // old record even when the new headline gives it a different filename.
+    let previous = ctx.state.latest_revision(&article.canonical_url)?;
+    let changed = !previous
+        .as_ref()
+        .is_some_and(|previous| previous.matches(&article));
+    if article.partial {
+        println!(
+            "Article at {} looks paywalled or truncated ({:?})",
+            link.url, article.paywall
+        );
+    }
+    if changed {
+        println!("Saving article at {}", link.url);
+        let path = ctx.store.save(&article)?;
+        ctx.state.add_revision(&article, previous.as_ref(), &path)?;
+        if let Some(previous) = previous.filter(|previous| previous.path != path) {
+            if let Err(err) = ctx.store.remove(&site.name, &previous.path) {
+                println!("Cannot remove stale record: {err}");
+            }
         }
     }
+    ctx.state.record_success(&site.name, &article, changed)
 }
 
-fn get_news_links(href: &str, a_attr: &str, link_prefix: &str) -> Result<Vec<String>> {
-    let response = get(href)?;
-    let text = response.text()?;
-    let doc = Html::parse_document(&text);
+// Articles are fetched as separate tasks; the fetcher's concurrency caps and
+// politeness rules decide how many of them are actually on the network.
+async fn scrape_website(site: Arc<NewsSite>, ctx: Arc<ScrapeContext>) -> SiteReport {
+    let mut report = SiteReport::default();
+
+    let extractor = ctx.registry.get(&site.name);
+    let mut source_failures = Vec::new();
+    let links = get_news_links(
+        &site,
+        extractor,
+        &ctx.fetcher,
+        &ctx.backfill,
+        &mut source_failures,
+    )
+    .await;
+    let no_source_failures = source_failures.is_empty();
+    for failure in source_failures {
+        report.add_failure(failure);
+    }
+    let links = match links {
+        Ok(links) => links,
+        Err(err) => {
+            println!("Cannot get links for {}: {err}", site.name);
+            // the failing sources are already in the report
+            if no_source_failures {
+                report.add_failure(Failure::new(&site.news_href, &err));
+            }
+            return report;
+        }
+    };
 
-    let link_selector = Selector::parse(a_attr).expect("Couldn't construct a selector.");
-    let links_elems = doc.select(&link_selector);
+    let from_source = |source| links.iter().filter(|link| link.source == source).count();
+    println!(
+        "Found {} links for {} ({} from feeds, {} from sitemaps)",
+        links.len(),
+        site.name,
+        from_source(LinkSource::Feed),
+        from_source(LinkSource::Sitemap)
+    );
 
-    let links = links_elems
+    let tasks: Vec<_> = links
         .into_iter()
-        .filter_map(|elem| {
-            let link_text = elem.value().attr("href")?;
-            if link_text.contains("/news/") {
-                let full_link = format!("https://{link_prefix}{link_text}");
-                Some(full_link)
-            } else {
-                None
+        .map(|link| {
+            let site = site.clone();
+            let ctx = ctx.clone();
+            let url = link.url.clone();
+            (
+                url,
+                tokio::spawn(async move { scrape_link(link, &site, &ctx).await }),
+            )
+        })
+        .collect();
+
+    for (url, task) in tasks {
+        let err = match task.await {
+            Ok(Ok(outcome)) => {
+                report.add(outcome);
+                continue;
             }
+            Ok(Err(err)) => err,
+            Err(err) => anyhow!(err),
+        };
+        println!("Cannot scrape article at {url}: {err:#}");
+        if let Err(state_err) = ctx
+            .state
+            .record_failure(&site.name, &url, &format!("{err:#}"))
+        {
+            println!("Cannot record failure for {url}: {state_err}");
+        }
+        report.add_failure(Failure::new(&url, &err));
+    }
+    report
+}
+
+async fn scrape_all(config: ScrapeConfig) -> Result<RunReport> {
+    let started = Utc::now();
+    let ctx = Arc::new(ScrapeContext {
+        registry: ExtractorRegistry::new(),
+        fetcher: Fetcher::new(&config.crawler)?,
+        state: CrawlState::open(Path::new(&config.crawler.state_path))?,
+        store: ArticleStore::new(Path::new(&config.crawler.output_dir)),
+        backfill: config.crawler.backfill,
+        refetch_window: Duration::days(config.crawler.refetch_days.into()),
+        claimed: Mutex::new(HashSet::new()),
+    });
+
+    let tasks: Vec<_> = config
+        .sites
+        .into_iter()
+        .map(|site| {
+            println!("Scraping {}...", site.name);
+            let name = site.name.clone();
+            (
+                name,
+                tokio::spawn(scrape_website(Arc::new(site), ctx.clone())),
+            )
         })
         .collect();
 
-    Ok(links)
+    let mut sites = BTreeMap::new();
+    for (name, task) in tasks {
+        sites.insert(name, task.await?);
+    }
+    let report = RunReport {
+        started,
+        finished: Utc::now(),
+        sites,
+    };
+
+    print!("{}", report.summary());
+    let report_path = report.save(Path::new(&config.crawler.report_dir))?;
+    println!("Run report saved at {}", report_path.display());
+    Ok(report)
 }
 
-struct NewsSite {
-    name: String,
-    a_attr: String,
-    body: String,
-    news_href: String,
-    link_prefix: String,
-    par_class: String,
+pub fn scrape_sites(config_path: &Path) -> Result<()> {
+    let config = load_config(config_path)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(scrape_all(config))?;
+    Ok(())
 }
 
-fn scrape_website(site: NewsSite) {
-    let links = match get_news_links(&site.news_href, &site.a_attr, &site.link_prefix) {
-        Ok(links) => links,
-        Err(err) => panic!("{err}"),
-    };
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
 
-    let path_root = Path::new("scraped/").join(&site.name);
+    use tempfile::TempDir;
 
-    for link in links {
-        let parse_result = parse_article(&link, &site);
-        if let Ok(article) = parse_result {
-            println!("Saving article at {link}");
-            save_article(&article, &path_root);
-        } else {
-            print!("Cannot parse article at {link}. Continuing...");
-        }
+    use super::*;
+    use crate::errors::ErrorCategory;
+    use crate::fixtures::FixtureStore;
+
+    // Pages recorded from the sites in tests/fixtures/replay/sites.toml.
+    fn fixtures_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay")
     }
-}
 
-pub fn scrape_cbc() {
-    let cbc = NewsSite {
-        name: "cbc".into(),
-        a_attr: "a.card".into(),
-        body: "div.story".into(),
-        news_href: "https://www.cbc.ca/news".into(),
-        link_prefix: "cbc.ca".into(),
-        par_class: "p".into(),
-    };
+    // The fixture config replaying into a fresh temporary directory.
+    fn replay_config(dir: &TempDir) -> ScrapeConfig {
+        let mut config = load_config(&fixtures_dir().join("sites.toml")).unwrap();
+        let path = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+        config.crawler.fixtures_dir = fixtures_dir().to_string_lossy().into_owned();
+        config.crawler.output_dir = path("scraped");
+        config.crawler.state_path = path("crawl_state.sqlite");
+        config.crawler.report_dir = path("reports");
+        config
+    }
 
-    scrape_website(cbc);
-}
+    #[test]
+    fn extracts_a_recorded_article() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = replay_config(&dir);
+        let site = &config.sites[0];
+        let url = "https://www.cbc.ca/news/canada/transit-strike-1.1";
+        let text = FixtureStore::open(&fixtures_dir())
+            .unwrap()
+            .load(url)
+            .unwrap();
+
+        let link = DiscoveredLink::from_html(url.into());
+        let article =
+            extract_article(&link, &text, site, ExtractorRegistry::new().get("cbc")).unwrap();
+        assert_eq!(article.title, "Transit workers walk off the job");
+        assert_eq!(article.canonical_url, url);
+        assert_eq!(article.authors, ["Jane Doe"]);
+        assert_eq!(article.published.to_rfc3339(), "2023-03-03T14:15:00+00:00");
+        assert_eq!(article.paragraphs.len(), 3);
+        assert!(article.paragraphs[0].starts_with("Transit workers across the city"));
+        assert!(!article.partial);
+    }
 
-pub fn scrape_np() {
-    let np = NewsSite {
-        name: "national_post".into(),
-        a_attr: "a.article-card__link".into(),
-        body: "section.article-content__content-group".into(),
-        news_href: "https://nationalpost.com/category/news/".into(),
-        link_prefix: "nationalpost.com".into(),
-        par_class: "p.section.article-content__content-group".into(),
-    };
+    #[test]
+    fn discovers_links_from_every_recorded_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = replay_config(&dir);
+        let site = &config.sites[0];
+        let fetcher = Fetcher::new(&config.crawler).unwrap();
+        let mut failures = Vec::new();
+
+        let links = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(get_news_links(
+                site,
+                ExtractorRegistry::new().get("cbc"),
+                &fetcher,
+                &config.crawler.backfill,
+                &mut failures,
+            ))
+            .unwrap();
+
+        let found: Vec<(&str, LinkSource)> = links
+            .iter()
+            .map(|link| (link.url.as_str(), link.source))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    "https://www.cbc.ca/news/canada/transit-strike-1.1",
+                    LinkSource::Feed
+                ),
+                (
+                    "https://www.cbc.ca/news/world/summit-1.3",
+                    LinkSource::Sitemap
+                ),
+                (
+                    "https://www.cbc.ca/news/canada/budget-vote-1.2",
+                    LinkSource::Html
+                ),
+                (
+                    "https://www.cbc.ca/news/transit-strike-1.1",
+                    LinkSource::Html
+                ),
+                (
+                    "https://www.cbc.ca/news/canada/pulled-story-1.4",
+                    LinkSource::Html
+                ),
+            ]
+        );
+        // the sitemap keeps the publication date and reports <lastmod> separately
+        let summit = &links[1];
+        assert_eq!(
+            summit.title.as_deref(),
+            Some("Leaders meet for climate summit")
+        );
+        assert_eq!(
+            summit.published.map(|date| date.to_rfc3339()).as_deref(),
+            Some("2023-03-03T16:30:00-05:00")
+        );
+        assert_eq!(
+            summit.updated.map(|date| date.to_rfc3339()).as_deref(),
+            Some("2023-03-04T08:00:00-05:00")
+        );
+        assert!(failures.is_empty());
+    }
 
-    scrape_website(np);
-}
+    #[test]
+    fn follows_template_pagination_across_sections() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = replay_config(&dir);
+        let site = &config.sites[1];
+        let fetcher = Fetcher::new(&config.crawler).unwrap();
+        let mut failures = Vec::new();
+
+        // every section and its second page are recorded, so a missing page
+        // would show up as a failure
+        let links = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(get_news_links(
+                site,
+                ExtractorRegistry::new().get("national_post"),
+                &fetcher,
+                &config.crawler.backfill,
+                &mut failures,
+            ))
+            .unwrap();
+
+        let found: Vec<(&str, LinkSource)> = links
+            .iter()
+            .map(|link| (link.url.as_str(), link.source))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    "https://nationalpost.com/news/canada/wildfire-season-starts-early",
+                    LinkSource::Feed
+                ),
+                (
+                    "https://nationalpost.com/news/world/ceasefire-talks-resume",
+                    LinkSource::Html
+                ),
+                // only linked from the second page of the news section
+                (
+                    "https://nationalpost.com/news/canada/housing-starts-fall",
+                    LinkSource::Html
+                ),
+            ]
+        );
+        assert!(failures.is_empty());
+    }
 
-pub fn scrape_star() {
-    let star = NewsSite {
-        name: "the_star".into(),
-        a_attr: "a.c-mediacard".into(),
-        body: "div.c-article-body__content".into(),
-        news_href: "https://www.thestar.com/news/world".into(),
-        link_prefix: "thestar.com".into(),
-        par_class: "p.text-block-container".into(),
-    };
+    #[test]
+    fn scrapes_every_recorded_site() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let report = runtime.block_on(scrape_all(replay_config(&dir))).unwrap();
+        let cbc = &report.sites["cbc"];
+        // the short transit-strike link is the feed's story again
+        assert_eq!(
+            (cbc.new, cbc.updated, cbc.duplicates, cbc.failed),
+            (3, 0, 1, 1)
+        );
+        assert_eq!(
+            cbc.failures[0].url,
+            "https://www.cbc.ca/news/canada/pulled-story-1.4"
+        );
+
+        let index = fs::read_to_string(dir.path().join("scraped/cbc/index.json")).unwrap();
+        let index: BTreeMap<String, serde_json::Value> = serde_json::from_str(&index).unwrap();
+        assert_eq!(index.len(), 3);
+        // the summit page has no date of its own and takes the sitemap's
+        assert!(index
+            .keys()
+            .any(|path| path.starts_with("2023-03-03/leaders-meet-for-climate-summit-")));
+
+        let national_post = &report.sites["national_post"];
+        assert_eq!((national_post.new, national_post.failed), (3, 0));
+        let index =
+            fs::read_to_string(dir.path().join("scraped/national_post/index.json")).unwrap();
+        let index: BTreeMap<String, serde_json::Value> = serde_json::from_str(&index).unwrap();
+        // paginated stories are saved too, with the outlet's own byline markup
+        let housing = index
+            .keys()
+            .find(|path| path.starts_with("2023-03-04/housing-starts-fall-"))
+            .unwrap();
+        let housing =
+            fs::read_to_string(dir.path().join("scraped/national_post").join(housing)).unwrap();
+        let housing: Article = serde_json::from_str(&housing).unwrap();
+        assert_eq!(housing.authors, ["John Roe", "Mary Major"]);
+
+        // the sponsored section is disallowed by the recorded robots.txt
+        let star = &report.sites["the_star"];
+        assert_eq!((star.new, star.failed), (2, 1));
+        assert_eq!(
+            star.failures[0].url,
+            "https://www.thestar.com/news/world/sponsored/winter-getaways.html"
+        );
+        assert_eq!(star.failures[0].category, ErrorCategory::RobotsDisallowed);
+
+        // A second run skips the dated stories and retries the failed one. The
+        // links only found on the section page have no date, so their age
+        // counts from the first run and they are rechecked inside the refetch
+        // window.
+        let report = runtime.block_on(scrape_all(replay_config(&dir))).unwrap();
+        let outcomes = |site: &str| {
+            let site = &report.sites[site];
+            (site.new, site.unchanged, site.skipped, site.failed)
+        };
+        assert_eq!(outcomes("cbc"), (0, 2, 2, 1));
+        assert_eq!(outcomes("national_post"), (0, 2, 1, 0));
+        assert_eq!(outcomes("the_star"), (0, 2, 0, 1));
+    }
 
-    scrape_website(star);
+    #[test]
+    fn second_run_fetches_nothing_already_seen() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let config = || {
+            let mut config = replay_config(&dir);
+            config.crawler.refetch_days = 0;
+            config
+        };
+
+        runtime.block_on(scrape_all(config())).unwrap();
+        let report = runtime.block_on(scrape_all(config())).unwrap();
+        // the duplicate is skipped like the story it repeats; only the failed
+        // stories are tried again
+        for (name, skipped, failed) in [("cbc", 4, 1), ("national_post", 3, 0), ("the_star", 2, 1)]
+        {
+            let site = &report.sites[name];
+            assert_eq!(
+                (site.new, site.updated, site.unchanged, site.duplicates),
+                (0, 0, 0, 0),
+                "{name}"
+            );
+            assert_eq!((site.skipped, site.failed), (skipped, failed), "{name}");
+        }
+    }
 }
@@ -0,0 +1,492 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use scraper::Selector;
+use serde::Deserialize;
+
+use crate::sitemaps::DateRange;
+use crate::text::TextRules;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewsSite {
+    pub name: String,
+    pub a_attr: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    pub news_href: String,
+    pub link_prefix: String,
+    #[serde(default = "default_par_class")]
+    pub par_class: String,
+    #[serde(default = "default_date_selectors")]
+    pub date_selectors: Vec<String>,
+    #[serde(default = "default_byline_selectors")]
+    pub byline_selectors: Vec<String>,
+    #[serde(default)]
+    pub title_suffixes: Vec<String>,
+    #[serde(default = "default_html_discovery")]
+    pub html_discovery: bool,
+    #[serde(default)]
+    pub feeds: Vec<String>,
+    #[serde(default)]
+    pub sitemaps: Vec<String>,
+    #[serde(default)]
+    pub robots_sitemaps: bool,
+    #[serde(default)]
+    pub sections: Vec<String>,
+    #[serde(default)]
+    pub pagination: Option<Pagination>,
+    #[serde(default = "default_max_pages")]
+    pub max_pages: usize,
+    #[serde(default)]
+    pub max_articles: Option<usize>,
+    #[serde(default)]
+    pub text: TextRules,
+    #[serde(default = "default_paywall_selectors")]
+    pub paywall_selectors: Vec<String>,
+}
+
+// How to get from one page of a section to the next. Templates substitute
+// `{url}` with the section URL and `{page}` with the page number, starting at 2.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Pagination {
+    NextLink {
+        selector: String,
+    },
+    Template {
+        template: String,
+    },
+    // infinite-scroll endpoints returning JSON; `links_pointer` is a JSON pointer
+    // to the array of items and `url_field` names the article URL in each item
+    Json {
+        template: String,
+        links_pointer: String,
+        url_field: String,
+    },
+}
+
+impl Pagination {
+    pub fn page_url(template: &str, section_url: &str, page: usize) -> String {
+        template
+            .replace("{url}", section_url)
+            .replace("{page}", &page.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CrawlerConfig {
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+    #[serde(default = "default_min_delay_ms")]
+    pub min_delay_ms: u64,
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    #[serde(default = "default_read_timeout_ms")]
+    pub read_timeout_ms: u64,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    #[serde(default = "default_backoff_base_ms")]
+    pub backoff_base_ms: u64,
+    #[serde(default = "default_backoff_max_ms")]
+    pub backoff_max_ms: u64,
+    #[serde(default = "default_max_redirects")]
+    pub max_redirects: usize,
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+    #[serde(default = "default_max_per_host")]
+    pub max_per_host: usize,
+    #[serde(default = "default_cache")]
+    pub cache: bool,
+    #[serde(default = "default_cache_dir")]
+    pub cache_dir: String,
+    #[serde(default)]
+    pub mode: FetchModeConfig,
+    #[serde(default = "default_fixtures_dir")]
+    pub fixtures_dir: String,
+    #[serde(default = "default_output_dir")]
+    pub output_dir: String,
+    #[serde(default)]
+    pub backfill: DateRange,
+    #[serde(default = "default_state_path")]
+    pub state_path: String,
+    #[serde(default = "default_refetch_days")]
+    pub refetch_days: u32,
+    #[serde(default = "default_report_dir")]
+    pub report_dir: String,
+    #[serde(default = "default_check_sample")]
+    pub check_sample: usize,
+    #[serde(default = "default_check_threshold")]
+    pub check_threshold: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FetchModeConfig {
+    #[default]
+    Live,
+    Record,
+    Replay,
+}
+
+impl Default for CrawlerConfig {
+    fn default() -> Self {
+        CrawlerConfig {
+            user_agent: default_user_agent(),
+            min_delay_ms: default_min_delay_ms(),
+            connect_timeout_ms: default_connect_timeout_ms(),
+            read_timeout_ms: default_read_timeout_ms(),
+            max_retries: default_max_retries(),
+            backoff_base_ms: default_backoff_base_ms(),
+            backoff_max_ms: default_backoff_max_ms(),
+            max_redirects: default_max_redirects(),
+            max_concurrency: default_max_concurrency(),
+            max_per_host: default_max_per_host(),
+            cache: default_cache(),
+            cache_dir: default_cache_dir(),
+            mode: FetchModeConfig::default(),
+            fixtures_dir: default_fixtures_dir(),
+            output_dir: default_output_dir(),
+            backfill: DateRange::default(),
+            state_path: default_state_path(),
+            refetch_days: default_refetch_days(),
+            report_dir: default_report_dir(),
+            check_sample: default_check_sample(),
+            check_threshold: default_check_threshold(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ScrapeConfig {
+    pub crawler: CrawlerConfig,
+    pub sites: Vec<NewsSite>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SitesFile {
+    #[serde(default)]
+    crawler: CrawlerConfig,
+    #[serde(rename = "site", default)]
+    sites: Vec<NewsSite>,
+}
+
+fn default_user_agent() -> String {
+    format!("news_scraper/{}", env!("CARGO_PKG_VERSION"))
+}
+
+fn default_min_delay_ms() -> u64 {
+    1000
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_read_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_backoff_base_ms() -> u64 {
+    500
+}
+
+fn default_backoff_max_ms() -> u64 {
+    30_000
+}
+
+fn default_max_redirects() -> usize {
+    10
+}
+
+fn default_max_concurrency() -> usize {
+    8
+}
+
+fn default_max_per_host() -> usize {
+    2
+}
+
+fn default_cache() -> bool {
+    true
+}
+
+fn default_cache_dir() -> String {
+    "scraped/.cache".into()
+}
+
+fn default_fixtures_dir() -> String {
+    "fixtures".into()
+}
+
+fn default_output_dir() -> String {
+    "scraped".into()
+}
+
+fn default_state_path() -> String {
+    "crawl_state.sqlite".into()
+}
+
+fn default_refetch_days() -> u32 {
+    2
+}
+
+fn default_report_dir() -> String {
+    "reports".into()
+}
+
+fn default_check_sample() -> usize {
+    5
+}
+
+fn default_check_threshold() -> f64 {
+    0.3
+}
+
+fn default_html_discovery() -> bool {
+    true
+}
+
+fn default_max_pages() -> usize {
+    1
+}
+
+fn default_par_class() -> String {
+    "p".into()
+}
+
+fn default_byline_selectors() -> Vec<String> {
+    vec![
+        "[rel=\"author\"]".into(),
+        "[itemprop=\"author\"] [itemprop=\"name\"]".into(),
+        ".byline".into(),
+    ]
+}
+
+fn default_paywall_selectors() -> Vec<String> {
+    vec![
+        ".paywall".into(),
+        ".subscriber-only".into(),
+        "[data-paywall]".into(),
+        ".tp-modal".into(),
+    ]
+}
+
+fn default_date_selectors() -> Vec<String> {
+    vec![
+        "time.timeStamp".into(),
+        "span.published-date__since".into(),
+        "span.article__published-date".into(),
+    ]
+}
+
+fn check_selector(site: &str, key: &str, selector: &str) -> Result<()> {
+    Selector::parse(selector).map(|_| ()).map_err(|err| {
+        anyhow!("site `{site}`: invalid selector in `{key}` ({selector:?}): {err:?}")
+    })
+}
+
+fn check_url(site: &str, key: &str, url: &str) -> Result<()> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "site `{site}`: `{key}` must be an http(s) URL, got {url:?}"
+        ))
+    }
+}
+
+fn validate_site(site: &NewsSite) -> Result<()> {
+    if site.name.trim().is_empty() {
+        return Err(anyhow!(
+            "site with news_href {:?}: `name` is empty",
+            site.news_href
+        ));
+    }
+    let name = &site.name;
+
+    check_url(name, "news_href", &site.news_href)?;
+    for (i, feed) in site.feeds.iter().enumerate() {
+        check_url(name, &format!("feeds[{i}]"), feed)?;
+    }
+    for (i, sitemap) in site.sitemaps.iter().enumerate() {
+        check_url(name, &format!("sitemaps[{i}]"), sitemap)?;
+    }
+    for (i, section) in site.sections.iter().enumerate() {
+        check_url(name, &format!("sections[{i}]"), section)?;
+    }
+    if site.max_pages == 0 {
+        return Err(anyhow!("site `{name}`: `max_pages` must be at least 1"));
+    }
+    if site.max_articles == Some(0) {
+        return Err(anyhow!(
+            "site `{name}`: `max_articles` must be at least 1 when set"
+        ));
+    }
+    match &site.pagination {
+        Some(Pagination::NextLink { selector }) => {
+            check_selector(name, "pagination.selector", selector)?
+        }
+        Some(Pagination::Template { template }) | Some(Pagination::Json { template, .. })
+            if !template.contains("{page}") =>
+        {
+            return Err(anyhow!(
+                "site `{name}`: `pagination.template` must contain {{page}}, got {template:?}"
+            ));
+        }
+        Some(Pagination::Json { links_pointer, .. })
+            if !links_pointer.is_empty() && !links_pointer.starts_with('/') =>
+        {
+            return Err(anyhow!(
+                "site `{name}`: `pagination.links_pointer` must be a JSON pointer like \"/items\", got {links_pointer:?}"
+            ));
+        }
+        _ => (),
+    }
+    if !site.html_discovery
+        && site.feeds.is_empty()
+        && site.sitemaps.is_empty()
+        && !site.robots_sitemaps
+    {
+        return Err(anyhow!(
+            "site `{name}`: `html_discovery` is off and no `feeds` or sitemaps are listed, so no links can be found"
+        ));
+    }
+    if site.link_prefix.contains("://") || site.link_prefix.ends_with('/') {
+        return Err(anyhow!(
+            "site `{name}`: `link_prefix` should be a bare host like \"cbc.ca\", got {:?}",
+            site.link_prefix
+        ));
+    }
+
+    check_selector(name, "a_attr", &site.a_attr)?;
+    if let Some(body) = &site.body {
+        check_selector(name, "body", body)?;
+    }
+    check_selector(name, "par_class", &site.par_class)?;
+    if site.date_selectors.is_empty() {
+        return Err(anyhow!("site `{name}`: `date_selectors` is empty"));
+    }
+    for (i, selector) in site.date_selectors.iter().enumerate() {
+        check_selector(name, &format!("date_selectors[{i}]"), selector)?;
+    }
+    for (i, selector) in site.byline_selectors.iter().enumerate() {
+        check_selector(name, &format!("byline_selectors[{i}]"), selector)?;
+    }
+    for (i, selector) in site.text.drop_selectors.iter().enumerate() {
+        check_selector(name, &format!("text.drop_selectors[{i}]"), selector)?;
+    }
+    for (i, selector) in site.paywall_selectors.iter().enumerate() {
+        check_selector(name, &format!("paywall_selectors[{i}]"), selector)?;
+    }
+
+    Ok(())
+}
+
+fn validate_sites(sites: &[NewsSite]) -> Result<()> {
+    let mut names = HashSet::new();
+    for site in sites {
+        validate_site(site)?;
+        if !names.insert(&site.name) {
+            return Err(anyhow!(
+                "site `{}`: `name` is defined more than once",
+                site.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn read_sites_file(path: &Path) -> Result<SitesFile> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Cannot read site config at {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("Invalid site config {}", path.display()))
+}
+
+fn read_site_dir(dir: &Path) -> Result<SitesFile> {
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("Cannot read site config directory {}", dir.display()))?
+        .map(|res| res.map(|e| e.path()))
+        .collect::<Result<Vec<_>, std::io::Error>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "toml"));
+    paths.sort();
+
+    // crawler.toml holds the [crawler] settings, every other file describes a
+    // single site with top-level keys
+    let crawler_path = dir.join("crawler.toml");
+    let crawler = if crawler_path.exists() {
+        read_sites_file(&crawler_path)?.crawler
+    } else {
+        CrawlerConfig::default()
+    };
+    paths.retain(|path| path != &crawler_path);
+
+    let sites = paths
+        .into_iter()
+        .map(|path| {
+            let text = fs::read_to_string(&path)
+                .with_context(|| format!("Cannot read site config at {}", path.display()))?;
+            toml::from_str(&text).with_context(|| format!("Invalid site config {}", path.display()))
+        })
+        .collect::<Result<Vec<NewsSite>>>()?;
+
+    Ok(SitesFile { crawler, sites })
+}
+
+pub fn load_config(path: &Path) -> Result<ScrapeConfig> {
+    let file = if path.is_dir() {
+        read_site_dir(path)?
+    } else {
+        read_sites_file(path)?
+    };
+
+    if file.sites.is_empty() {
+        return Err(anyhow!("No sites defined in {}", path.display()));
+    }
+    validate_sites(&file.sites)
+        .with_context(|| format!("Invalid site config {}", path.display()))?;
+    if file.crawler.user_agent.trim().is_empty() {
+        return Err(anyhow!(
+            "Invalid site config {}: `crawler.user_agent` is empty",
+            path.display()
+        ));
+    }
+    if let (Some(since), Some(until)) = (file.crawler.backfill.since, file.crawler.backfill.until) {
+        if since > until {
+            return Err(anyhow!(
+                "Invalid site config {}: `crawler.backfill.since` is after `crawler.backfill.until`",
+                path.display()
+            ));
+        }
+    }
+    if file.crawler.max_concurrency == 0 || file.crawler.max_per_host == 0 {
+        return Err(anyhow!(
+            "Invalid site config {}: `crawler.max_concurrency` and `crawler.max_per_host` must be at least 1",
+            path.display()
+        ));
+    }
+    if file.crawler.check_sample == 0 {
+        return Err(anyhow!(
+            "Invalid site config {}: `crawler.check_sample` must be at least 1",
+            path.display()
+        ));
+    }
+    if !(file.crawler.check_threshold > 0.0 && file.crawler.check_threshold <= 1.0) {
+        return Err(anyhow!(
+            "Invalid site config {}: `crawler.check_threshold` must be between 0 and 1, got {}",
+            path.display(),
+            file.crawler.check_threshold
+        ));
+    }
+
+    Ok(ScrapeConfig {
+        crawler: file.crawler,
+        sites: file.sites,
+    })
+}
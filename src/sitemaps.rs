@@ -0,0 +1,217 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use roxmltree::{Document, Node};
+use serde::Deserialize;
+
+use crate::discovery::{DiscoveredLink, LinkSource};
+use crate::xml::{child, child_date, child_text};
+
+// Inclusive range of publication dates to backfill. Entries without any date
+// are kept, since there is no way to tell whether they fall inside the range.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DateRange {
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn contains(&self, date: Option<DateTime<FixedOffset>>) -> bool {
+        let Some(date) = date else {
+            return true;
+        };
+        let date = date.date_naive();
+        self.since.is_none_or(|since| date >= since) && self.until.is_none_or(|until| date <= until)
+    }
+
+    // A sitemap last modified before `since` can't list anything newer.
+    pub fn may_contain(&self, lastmod: Option<DateTime<FixedOffset>>) -> bool {
+        match (lastmod, self.since) {
+            (Some(lastmod), Some(since)) => lastmod.date_naive() >= since,
+            _ => true,
+        }
+    }
+}
+
+pub struct SitemapEntry {
+    pub url: String,
+    pub lastmod: Option<DateTime<FixedOffset>>,
+}
+
+// A sitemap index lists further sitemaps; a urlset lists pages.
+pub enum Sitemap {
+    Index(Vec<SitemapEntry>),
+    UrlSet(Vec<DiscoveredLink>),
+}
+
+// Google News sitemaps add <news:news> with the headline and publication date,
+// which are more precise than <lastmod>.
+fn parse_url(url: Node) -> Option<DiscoveredLink> {
+    let news = child(url, "news");
+    let lastmod = child_date(url, "lastmod");
+
+    Some(DiscoveredLink {
+        url: child_text(url, "loc")?,
+        source: LinkSource::Sitemap,
+        title: news.and_then(|news| child_text(news, "title")),
+        published: news
+            .and_then(|news| child_date(news, "publication_date"))
+            .or(lastmod),
+        updated: lastmod,
+        guid: None,
+    })
+}
+
+pub fn parse_sitemap(xml: &str) -> Result<Sitemap> {
+    let doc = Document::parse(xml)?;
+    let root = doc.root_element();
+    let elements = |name: &'static str| {
+        root.children()
+            .filter(move |node| node.is_element() && node.tag_name().name() == name)
+    };
+
+    match root.tag_name().name() {
+        "sitemapindex" => Ok(Sitemap::Index(
+            elements("sitemap")
+                .filter_map(|sitemap| {
+                    Some(SitemapEntry {
+                        url: child_text(sitemap, "loc")?,
+                        lastmod: child_date(sitemap, "lastmod"),
+                    })
+                })
+                .collect(),
+        )),
+        "urlset" => Ok(Sitemap::UrlSet(
+            elements("url").filter_map(parse_url).collect(),
+        )),
+        other => Err(anyhow!("Unknown sitemap format <{other}>.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(raw: &str) -> Option<DateTime<FixedOffset>> {
+        Some(DateTime::parse_from_rfc3339(raw).unwrap())
+    }
+
+    fn day(raw: &str) -> Option<NaiveDate> {
+        Some(raw.parse().unwrap())
+    }
+
+    #[test]
+    fn reads_a_sitemap_index() {
+        let Sitemap::Index(entries) = parse_sitemap(
+            r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <sitemap>
+                <loc>https://example.com/sitemap-2023-03.xml.gz</loc>
+                <lastmod>2023-03-31T23:00:00Z</lastmod>
+              </sitemap>
+              <sitemap><loc>https://example.com/sitemap-news.xml</loc></sitemap>
+              <sitemap><lastmod>2023-03-01</lastmod></sitemap>
+            </sitemapindex>"#,
+        )
+        .unwrap() else {
+            panic!("expected a sitemap index");
+        };
+        let entries: Vec<(&str, Option<String>)> = entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.url.as_str(),
+                    entry.lastmod.map(|date| date.to_rfc3339()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (
+                    "https://example.com/sitemap-2023-03.xml.gz",
+                    Some("2023-03-31T23:00:00+00:00".to_string())
+                ),
+                ("https://example.com/sitemap-news.xml", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn news_publication_date_wins_over_lastmod() {
+        let Sitemap::UrlSet(links) = parse_sitemap(
+            r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9"
+                       xmlns:news="http://www.google.com/schemas/sitemap-news/0.9">
+              <url>
+                <loc>https://example.com/news/summit</loc>
+                <lastmod>2023-03-04T08:00:00-05:00</lastmod>
+                <news:news>
+                  <news:title>Leaders meet for climate summit</news:title>
+                  <news:publication_date>2023-03-03T16:30:00-05:00</news:publication_date>
+                </news:news>
+              </url>
+              <url>
+                <loc>https://example.com/news/plain</loc>
+                <lastmod>2023-03-05T10:00:00Z</lastmod>
+              </url>
+            </urlset>"#,
+        )
+        .unwrap() else {
+            panic!("expected a urlset");
+        };
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].source, LinkSource::Sitemap);
+        assert_eq!(
+            links[0].title.as_deref(),
+            Some("Leaders meet for climate summit")
+        );
+        assert_eq!(links[0].published, date("2023-03-03T16:30:00-05:00"));
+        assert_eq!(links[0].updated, date("2023-03-04T08:00:00-05:00"));
+        // without news markup the last modification is all there is
+        assert_eq!(links[1].title, None);
+        assert_eq!(links[1].published, date("2023-03-05T10:00:00Z"));
+        assert_eq!(links[1].updated, links[1].published);
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(parse_sitemap("<rss><channel/></rss>").is_err());
+    }
+
+    #[test]
+    fn date_ranges_include_both_ends() {
+        let range = DateRange {
+            since: day("2023-03-01"),
+            until: day("2023-03-31"),
+        };
+        assert!(range.contains(date("2023-03-01T00:00:00Z")));
+        assert!(range.contains(date("2023-03-31T23:59:59Z")));
+        assert!(!range.contains(date("2023-02-28T23:59:59Z")));
+        assert!(!range.contains(date("2023-04-01T00:00:00Z")));
+        // the day is taken in the page's own offset
+        assert!(range.contains(date("2023-03-31T22:00:00-05:00")));
+        assert!(!range.contains(date("2023-02-28T23:30:00-05:00")));
+        // undated pages can't be ruled out
+        assert!(range.contains(None));
+
+        let open = DateRange {
+            since: None,
+            until: day("2023-03-31"),
+        };
+        assert!(open.contains(date("1999-01-01T00:00:00Z")));
+        assert!(DateRange::default().contains(date("2023-03-15T12:00:00Z")));
+    }
+
+    #[test]
+    fn sitemaps_last_modified_before_the_range_are_skipped() {
+        let range = DateRange {
+            since: day("2023-03-01"),
+            until: day("2023-03-31"),
+        };
+        assert!(range.may_contain(date("2023-03-01T00:00:00Z")));
+        assert!(!range.may_contain(date("2023-02-28T23:59:59Z")));
+        // a sitemap modified after the range may still list pages inside it
+        assert!(range.may_contain(date("2023-06-01T00:00:00Z")));
+        assert!(range.may_contain(None));
+        assert!(DateRange::default().may_contain(date("2001-01-01T00:00:00Z")));
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::article::Article;
+use crate::response_cache::{sha256_hex, write_atomic};
+
+// Long enough to tell headlines apart, short enough that the date directory,
+// slug and hash stay well under common path limits.
+const MAX_SLUG_LEN: usize = 80;
+const HASH_LEN: usize = 8;
+const INDEX_FILE: &str = "index.json";
+
+// Lowercase words joined by '-': accents are stripped after NFKD normalization
+// and anything that isn't a letter or digit separates words.
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.nfkd().filter(|c| !is_combining_mark(*c)) {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let mut slug = slug.trim_end_matches('-').to_string();
+    if slug.len() > MAX_SLUG_LEN {
+        let mut end = MAX_SLUG_LEN;
+        while !slug.is_char_boundary(end) {
+            end -= 1;
+        }
+        // cut at a word boundary where there is one
+        slug.truncate(slug[..end].rfind('-').unwrap_or(end));
+    }
+    if slug.is_empty() {
+        slug.push_str("article");
+    }
+    slug
+}
+
+// `<slug>-<hash>.json`, where the hash comes from the canonical URL so the same
+// story keeps its filename across runs and same-day headlines can't collide.
+pub fn article_filename(article: &Article) -> String {
+    let hash = sha256_hex(article.canonical_url.as_bytes());
+    format!("{}-{}.json", slugify(&article.title), &hash[..HASH_LEN])
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub title: String,
+    pub canonical_url: String,
+}
+
+// Writes article records under `<root>/<site>/<YYYY-MM-DD>/` and keeps an
+// `index.json` per site mapping each record's relative path back to its title.
+pub struct ArticleStore {
+    root: PathBuf,
+    indexes: Mutex<HashMap<String, BTreeMap<String, IndexEntry>>>,
+}
+
+fn load_index(path: &Path) -> Result<BTreeMap<String, IndexEntry>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Cannot read slug index {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("Invalid slug index {}", path.display()))
+}
+
+impl ArticleStore {
+    pub fn new(root: &Path) -> Self {
+        ArticleStore {
+            root: root.to_path_buf(),
+            indexes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // The index is changed and rewritten under the lock so concurrent saves
+    // don't drop entries.
+    fn update_index<F>(&self, site: &str, update: F) -> Result<()>
+    where
+        F: FnOnce(&mut BTreeMap<String, IndexEntry>),
+    {
+        let site_dir = self.root.join(site);
+        let index_path = site_dir.join(INDEX_FILE);
+        let mut indexes = self.indexes.lock().unwrap();
+        if !indexes.contains_key(site) {
+            indexes.insert(site.to_string(), load_index(&index_path)?);
+        }
+        let index = indexes.get_mut(site).expect("Index was just loaded.");
+        update(index);
+        fs::create_dir_all(&site_dir)?;
+        write_atomic(
+            &index_path,
+            serde_json::to_string_pretty(&*index)?.as_bytes(),
+        )
+    }
+
+    pub fn save(&self, article: &Article) -> Result<PathBuf> {
+        let day = article.published.format("%Y-%m-%d").to_string();
+        let relative = format!("{day}/{}", article_filename(article));
+        let path = self.root.join(&article.site).join(&relative);
+        fs::create_dir_all(path.parent().expect("Article path has a date directory."))?;
+        write_atomic(&path, serde_json::to_string_pretty(article)?.as_bytes())?;
+
+        let entry = IndexEntry {
+            title: article.title.clone(),
+            canonical_url: article.canonical_url.clone(),
+        };
+        self.update_index(&article.site, |index| {
+            index.insert(relative, entry);
+        })?;
+        Ok(path)
+    }
+
+    pub fn remove(&self, site: &str, path: &Path) -> Result<()> {
+        fs::remove_file(path).with_context(|| format!("Cannot remove {}", path.display()))?;
+        let site_dir = self.root.join(site);
+        if let Ok(relative) = path.strip_prefix(&site_dir) {
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            self.update_index(site, |index| {
+                index.remove(&relative);
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugs_are_lowercase_words_without_accents() {
+        assert_eq!(
+            slugify("Québec's Premier: \"No Deal\" — Yet"),
+            "quebec-s-premier-no-deal-yet"
+        );
+        assert_eq!(slugify("  Ontario  budget 2023!  "), "ontario-budget-2023");
+    }
+
+    #[test]
+    fn slugs_keep_letters_outside_ascii() {
+        assert_eq!(slugify("Straße nach Київ"), "straße-nach-киів");
+        assert_eq!(slugify("東京 Olympics"), "東京-olympics");
+    }
+
+    #[test]
+    fn empty_slugs_fall_back_to_article() {
+        assert_eq!(slugify(""), "article");
+        assert_eq!(slugify("— ... —"), "article");
+    }
+
+    #[test]
+    fn long_slugs_are_cut_at_a_word_boundary() {
+        let slug = slugify(&"breaking news ".repeat(20));
+        assert!(slug.len() <= MAX_SLUG_LEN);
+        assert!(slug.ends_with("-breaking"));
+
+        let slug = slugify(&"x".repeat(100));
+        assert_eq!(slug.len(), MAX_SLUG_LEN);
+    }
+}
@@ -0,0 +1,254 @@
+use scraper::{ElementRef, Node, Selector};
+use serde::{Deserialize, Serialize};
+
+// Elements inside a paragraph that start a new line of text when rendered.
+const BREAKING_TAGS: [&str; 7] = ["br", "div", "p", "li", "ul", "ol", "blockquote"];
+
+// Per-site rules for turning paragraph elements into plain text.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextRules {
+    // elements dropped along with everything inside them
+    #[serde(default = "default_drop_selectors")]
+    pub drop_selectors: Vec<String>,
+    // boilerplate text removed wherever it appears
+    #[serde(default = "default_drop_phrases")]
+    pub drop_phrases: Vec<String>,
+    // record the links in each paragraph alongside the text
+    #[serde(default)]
+    pub keep_links: bool,
+}
+
+impl Default for TextRules {
+    fn default() -> Self {
+        TextRules {
+            drop_selectors: default_drop_selectors(),
+            drop_phrases: default_drop_phrases(),
+            keep_links: false,
+        }
+    }
+}
+
+fn default_drop_selectors() -> Vec<String> {
+    vec![
+        "script".into(),
+        "style".into(),
+        "noscript".into(),
+        "iframe".into(),
+        "svg".into(),
+        "button".into(),
+        "figcaption".into(),
+    ]
+}
+
+fn default_drop_phrases() -> Vec<String> {
+    // National Post repeats this label through its article bodies
+    vec!["Article content".into()]
+}
+
+// A link found in a paragraph, by index into the article's paragraphs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextLink {
+    pub paragraph: usize,
+    pub text: String,
+    pub href: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Paragraph {
+    pub text: String,
+    // (link text, href) pairs, only collected when `keep_links` is set
+    pub links: Vec<(String, String)>,
+}
+
+// Numbers each paragraph's links and splits them from the text.
+pub fn split_links(paragraphs: Vec<Paragraph>) -> (Vec<String>, Vec<TextLink>) {
+    let mut texts = Vec::new();
+    let mut links = Vec::new();
+    for (i, paragraph) in paragraphs.into_iter().enumerate() {
+        texts.push(paragraph.text);
+        links.extend(paragraph.links.into_iter().map(|(text, href)| TextLink {
+            paragraph: i,
+            text,
+            href,
+        }));
+    }
+    (texts, links)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Walks a paragraph's element tree rather than serializing it, so inline
+// markup never leaks into the text and entities arrive decoded.
+pub struct TextNormalizer {
+    drop_selectors: Vec<Selector>,
+    drop_phrases: Vec<String>,
+    keep_links: bool,
+}
+
+impl TextNormalizer {
+    pub fn new(rules: &TextRules) -> Self {
+        TextNormalizer {
+            drop_selectors: rules
+                .drop_selectors
+                .iter()
+                .map(|selector| Selector::parse(selector).expect("Unable to construct selector."))
+                .collect(),
+            drop_phrases: rules.drop_phrases.clone(),
+            keep_links: rules.keep_links,
+        }
+    }
+
+    fn is_dropped(&self, elem: &ElementRef) -> bool {
+        self.drop_selectors
+            .iter()
+            .any(|selector| selector.matches(elem))
+    }
+
+    fn walk(&self, elem: ElementRef, text: &mut String, links: &mut Vec<(String, String)>) {
+        for child in elem.children() {
+            match child.value() {
+                Node::Text(node_text) => text.push_str(node_text),
+                Node::Element(child_elem) => {
+                    let Some(child_ref) = ElementRef::wrap(child) else {
+                        continue;
+                    };
+                    if self.is_dropped(&child_ref) {
+                        continue;
+                    }
+                    let breaking = BREAKING_TAGS.contains(&child_elem.name());
+                    if breaking {
+                        text.push(' ');
+                    }
+
+                    let start = text.len();
+                    self.walk(child_ref, text, links);
+                    if self.keep_links && child_elem.name() == "a" {
+                        let link_text = collapse_whitespace(&text[start..]);
+                        if let Some(href) = child_elem.attr("href") {
+                            if !link_text.is_empty() {
+                                links.push((link_text, href.trim().to_string()));
+                            }
+                        }
+                    }
+
+                    if breaking {
+                        text.push(' ');
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    // None for paragraphs that are empty once boilerplate is gone.
+    pub fn paragraph(&self, elem: ElementRef) -> Option<Paragraph> {
+        if self.is_dropped(&elem) {
+            return None;
+        }
+        let mut raw = String::new();
+        let mut links = Vec::new();
+        self.walk(elem, &mut raw, &mut links);
+
+        let mut text = collapse_whitespace(&raw);
+        for phrase in &self.drop_phrases {
+            if text.contains(phrase.as_str()) {
+                text = collapse_whitespace(&text.replace(phrase.as_str(), " "));
+            }
+        }
+        if text.is_empty() {
+            return None;
+        }
+        Some(Paragraph { text, links })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scraper::Html;
+
+    use super::*;
+
+    fn paragraph(html: &str, rules: &TextRules) -> Option<Paragraph> {
+        let doc = Html::parse_fragment(html);
+        let selector = Selector::parse("p").unwrap();
+        let elem = doc.select(&selector).next().unwrap();
+        TextNormalizer::new(rules).paragraph(elem)
+    }
+
+    fn text(html: &str) -> Option<String> {
+        paragraph(html, &TextRules::default()).map(|paragraph| paragraph.text)
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(
+            text("<p>Tom &amp; Jerry&#39;s &ldquo;caf&eacute;&rdquo;&nbsp;menu</p>").unwrap(),
+            "Tom & Jerry's \u{201c}café\u{201d} menu"
+        );
+    }
+
+    #[test]
+    fn collapses_whitespace_across_inline_tags() {
+        assert_eq!(
+            text("<p>  The <em>mayor</em>\n   said <strong>no</strong>.\t</p>").unwrap(),
+            "The mayor said no."
+        );
+        // inline tags inside a word don't split it
+        assert_eq!(text("<p>un<b>believ</b>able</p>").unwrap(), "unbelievable");
+    }
+
+    #[test]
+    fn line_breaks_separate_words() {
+        assert_eq!(
+            text("<p>First line<br>second line<br/>third</p>").unwrap(),
+            "First line second line third"
+        );
+    }
+
+    #[test]
+    fn drops_selected_elements() {
+        let rules = TextRules {
+            drop_selectors: vec!["script".into(), "button".into(), "span.ad".into()],
+            ..TextRules::default()
+        };
+        let html = "<p>The story<script>track()</script> goes<button>Share</button> on\
+                    <span class=\"ad\">Advertisement</span>.</p>";
+        assert_eq!(paragraph(html, &rules).unwrap().text, "The story goes on.");
+        // a paragraph matching a selector is dropped whole
+        let html = "<p class=\"ad\"><span class=\"ad\">Advertisement</span></p>";
+        assert!(paragraph(html, &rules).is_none());
+    }
+
+    #[test]
+    fn drops_boilerplate_phrases() {
+        assert_eq!(
+            text("<p>Article content The story continues. Article content</p>").unwrap(),
+            "The story continues."
+        );
+        assert!(text("<p> Article content </p>").is_none());
+    }
+
+    #[test]
+    fn captures_links_only_when_asked() {
+        let html = "<p>Read <a href=\" /report \">the <em>full</em>\n report</a> and \
+                    <a href=\"/empty\"> </a>more.</p>";
+        let rules = TextRules {
+            keep_links: true,
+            ..TextRules::default()
+        };
+        let kept = paragraph(html, &rules).unwrap();
+        assert_eq!(kept.text, "Read the full report and more.");
+        assert_eq!(
+            kept.links,
+            [("the full report".to_string(), "/report".to_string())]
+        );
+
+        assert!(paragraph(html, &TextRules::default())
+            .unwrap()
+            .links
+            .is_empty());
+    }
+}
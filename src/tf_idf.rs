@@ -1,10 +1,9 @@
 use anyhow::{anyhow, Result};
-use nlprule::Tokenizer;
 use std::collections::hash_map::RandomState;
 use std::collections::{HashMap, HashSet};
 
 use crate::prepare_dataset::ArticleEntry;
-use crate::preprocess::{tokenize_headline_text, tokenize_article_text, read_article_text};
+use crate::preprocess::{tokenize_headline_text, tokenize_article_text, read_article_text, TokenizerRegistry};
 
 pub struct DocumentFrequencyCounter {
     pub counter: HashMap<String, usize>,
@@ -54,12 +53,12 @@ impl DocumentFrequencyCounter {
 }
 
 pub fn get_token_headline_frequencies(
-    tokenizer: &Tokenizer,
+    tokenizers: &TokenizerRegistry,
     train_set: &Vec<ArticleEntry>,
 ) -> Result<DocumentFrequencyCounter> {
     let mut counter: HashMap<String, usize> = HashMap::new();
     for entry in train_set {
-        let tokens = tokenize_headline_text(tokenizer, &entry.path)?;
+        let tokens = tokenize_headline_text(tokenizers, entry)?;
         tokens.into_iter().for_each(|token| {
             counter.entry(token).and_modify(|e| *e += 1).or_insert(1);
         });
@@ -69,12 +68,12 @@ pub fn get_token_headline_frequencies(
 }
 
 pub fn get_token_article_frequencies(
-    tokenizer: &Tokenizer,
+    tokenizers: &TokenizerRegistry,
     train_set: &Vec<ArticleEntry>,
 ) -> Result<DocumentFrequencyCounter> {
     let mut counter: HashMap<String, usize> = HashMap::new();
     for entry in train_set {
-        let tokens = tokenize_article_text(tokenizer, &entry.path);
+        let tokens = tokenize_article_text(tokenizers, entry);
         match tokens {
             Ok(seq) => {
                 let token_set: HashSet<String> = HashSet::from_iter(seq);
@@ -112,8 +111,8 @@ pub fn get_tf_idf(counter: &DocumentFrequencyCounter, token: &String, headline_t
     }
 }
 
-pub fn entry_into_headline_tfidf(counter: &DocumentFrequencyCounter, tokenizer: &Tokenizer, entry: &ArticleEntry) -> Result<Vec<(String, f32)>> {
-    let headline_tokens = tokenize_headline_text(tokenizer, &entry.path)?;
+pub fn entry_into_headline_tfidf(counter: &DocumentFrequencyCounter, tokenizers: &TokenizerRegistry, entry: &ArticleEntry) -> Result<Vec<(String, f32)>> {
+    let headline_tokens = tokenize_headline_text(tokenizers, entry)?;
     let mut tf_idf_results: Vec<(String, f32)> = Vec::new();
 
     for token in &headline_tokens {
@@ -123,8 +122,8 @@ pub fn entry_into_headline_tfidf(counter: &DocumentFrequencyCounter, tokenizer:
     Ok(tf_idf_results)
 }
 
-pub fn entry_into_article_tfidf(counter: &DocumentFrequencyCounter, tokenizer: &Tokenizer, entry: &ArticleEntry) -> Result<Vec<TfIdfResult>> {
-    let article_tokens = tokenize_article_text(tokenizer, &entry.path)?;
+pub fn entry_into_article_tfidf(counter: &DocumentFrequencyCounter, tokenizers: &TokenizerRegistry, entry: &ArticleEntry) -> Result<Vec<TfIdfResult>> {
+    let article_tokens = tokenize_article_text(tokenizers, entry)?;
     let mut tf_idf_results: Vec<TfIdfResult> = Vec::new();
 
     for token in &article_tokens {
@@ -134,10 +133,10 @@ pub fn entry_into_article_tfidf(counter: &DocumentFrequencyCounter, tokenizer: &
     Ok(tf_idf_results)
 }
 
-pub fn get_tfidf_distribution(counter: &DocumentFrequencyCounter, tokenizer: &Tokenizer, train_ds: &Vec<ArticleEntry>) -> Result<Vec<(u64, usize)>> {
+pub fn get_tfidf_distribution(counter: &DocumentFrequencyCounter, tokenizers: &TokenizerRegistry, train_ds: &Vec<ArticleEntry>) -> Result<Vec<(u64, usize)>> {
     let mut dist_counter: HashMap<u64, usize> = HashMap::new();
     for entry in train_ds {
-        let tf_idf_results = entry_into_article_tfidf(counter, tokenizer, entry);
+        let tf_idf_results = entry_into_article_tfidf(counter, tokenizers, entry);
         match tf_idf_results {
             Ok(res) => {
                 let mut tf_idf_set: HashSet<_> = HashSet::from_iter(res.iter());
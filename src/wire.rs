@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use crate::response_cache::sha256_hex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireService {
+    CanadianPress,
+    AssociatedPress,
+    Reuters,
+}
+
+impl WireService {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WireService::CanadianPress => "The Canadian Press",
+            WireService::AssociatedPress => "The Associated Press",
+            WireService::Reuters => "Reuters",
+        }
+    }
+}
+
+// A story from a wire service. The fingerprint is shared by every outlet that
+// republishes the same copy, even under a different headline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WireStory {
+    pub service: WireService,
+    pub fingerprint: String,
+}
+
+// Credits as they appear in bylines and sign-offs ("This report by The
+// Canadian Press was first published ...").
+const CREDITS: [(&str, WireService); 3] = [
+    ("canadian press", WireService::CanadianPress),
+    ("associated press", WireService::AssociatedPress),
+    ("reuters", WireService::Reuters),
+];
+
+// Datelines only count parenthesized credits ("OTTAWA (Reuters) -"), since a
+// lede can mention a service in passing.
+const DATELINE_CREDITS: [(&str, WireService); 4] = [
+    ("(cp)", WireService::CanadianPress),
+    ("(the canadian press)", WireService::CanadianPress),
+    ("(ap)", WireService::AssociatedPress),
+    ("(reuters)", WireService::Reuters),
+];
+
+// datelines and sign-offs are short; longer text mentioning a service is news
+const MAX_CREDIT_LEN: usize = 200;
+// paragraphs shorter than this are datelines, credits and photo captions
+const MIN_FINGERPRINT_LEN: usize = 80;
+const FINGERPRINT_PARAGRAPHS: usize = 3;
+
+fn credited_service(text: &str, credits: &[(&str, WireService)]) -> Option<WireService> {
+    let text = text.to_lowercase();
+    credits
+        .iter()
+        .find(|(credit, _)| text.contains(credit))
+        .map(|(_, service)| *service)
+}
+
+pub fn detect_wire(authors: &[String], paragraphs: &[String]) -> Option<WireService> {
+    let byline = authors
+        .iter()
+        .find_map(|author| credited_service(author, &CREDITS));
+    let dateline = || {
+        let first = paragraphs.first()?;
+        let dateline: String = first.chars().take(MAX_CREDIT_LEN).collect();
+        credited_service(&dateline, &DATELINE_CREDITS)
+    };
+    let sign_off = || {
+        let last = paragraphs
+            .last()
+            .filter(|last| last.len() <= MAX_CREDIT_LEN)?;
+        credited_service(last, &CREDITS)
+    };
+    byline.or_else(dateline).or_else(sign_off)
+}
+
+// Outlets restyle datelines and credits but rarely touch the body, so the
+// fingerprint hashes the first few substantial paragraphs after the lede,
+// lowercased and stripped of punctuation.
+pub fn story_fingerprint(paragraphs: &[String]) -> Option<String> {
+    let normalized: Vec<String> = paragraphs
+        .iter()
+        .map(|par| {
+            par.to_lowercase()
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+                .collect::<String>()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|par| par.len() >= MIN_FINGERPRINT_LEN)
+        .collect();
+
+    if normalized.is_empty() {
+        return None;
+    }
+    // a lone paragraph is all there is to go on
+    let skip = usize::from(normalized.len() > 1);
+    let body = normalized[skip..]
+        .iter()
+        .take(FINGERPRINT_PARAGRAPHS)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(sha256_hex(body.as_bytes()))
+}
+
+pub fn wire_story(authors: &[String], paragraphs: &[String]) -> Option<WireStory> {
+    Some(WireStory {
+        service: detect_wire(authors, paragraphs)?,
+        fingerprint: story_fingerprint(paragraphs)?,
+    })
+}
@@ -0,0 +1,25 @@
+use chrono::{DateTime, FixedOffset};
+use roxmltree::Node;
+
+use crate::dates::{self, parse_pub_date};
+
+// Helpers for the feed and sitemap parsers, which match elements on their local
+// name so namespaced tags like <news:title> and <dc:date> are found too.
+
+pub fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.is_element() && child.tag_name().name() == name)
+}
+
+pub fn child_text(node: Node, name: &str) -> Option<String> {
+    let text = child(node, name)?.text()?.trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(String::from(text))
+    }
+}
+
+pub fn child_date(node: Node, name: &str) -> Option<DateTime<FixedOffset>> {
+    parse_pub_date(&child_text(node, name)?, dates::now()).ok()
+}